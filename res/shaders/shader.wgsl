@@ -17,12 +17,30 @@ struct RenderData {
     wind_direction: f32,
     wind_noise_scale: f32,
     wind_noise_strength: f32,
-    padding: f32
+    noise_seed: f32,
+    biome_centers: vec4<f32>,
+    biome_tints: array<vec4<f32>, 4>,
+    biome_scale: f32,
+    biome_blend: f32,
+    biome_count: f32,
+    biome_padding: f32,
 };
 
+const MAX_BIOMES: i32 = 4;
+// matches GROUND_OCTAVES in noise.rs
+const GROUND_OCTAVES: i32 = 3;
+// matches WIND_NOISE_OFFSET in particles.rs
+const WIND_NOISE_OFFSET: vec2<f32> = vec2<f32>(31.7, 11.3);
+const PATH_COLOR: vec3<f32> = vec3<f32>(0.55, 0.47, 0.36);
+// match PATH_WIDTH and PATH_EDGE in paths.rs
+const PATH_WIDTH: f32 = 0.35;
+const PATH_EDGE: f32 = 0.1;
+
 @group(1) @binding(0)
 var<uniform> render_data: RenderData;
 
+// only the fine grain of the ground, it's too small for the repeats to show and much cheaper than
+// ground_noise per fragment, anything that has to match the cpu uses ground_noise
 @group(2) @binding(0)
 var tex: texture_2d<f32>;
 @group(2) @binding(1)
@@ -51,12 +69,33 @@ struct InstanceInput{
     @location(12) inv_model_matrix_3: vec4<f32>,
 }
 
+// matches GrassInstance
+struct GrassInput{
+    @location(5) position: vec3<f32>,
+    @location(6) height: f32,
+    @location(7) width: f32,
+    @location(8) bend: f32,
+    @location(9) color_offset: f32,
+    @location(10) seed: f32,
+}
+
+// matches ParticleInstance
+struct ParticleInput{
+    @location(5) position: vec3<f32>,
+    @location(6) size: f32,
+    @location(7) color: f32,
+    @location(8) rotation: f32,
+    @location(9) tilt: f32,
+}
+
 struct VertexOutput{
     @builtin(position) clip_position: vec4<f32>,
     @location(0) normal: vec3<f32>,
     @location(1) age: f32,
     @location(2) world_pos: vec3<f32>,
     @location(3) scale: vec3<f32>,
+    @location(4) origin: vec3<f32>,
+    @location(5) color_offset: f32,
 };
 
 @vertex
@@ -88,7 +127,8 @@ fn vs_main(
     let scale = vec3<f32>(scale1, scale2, scale3);
 
     let wind = calculate_wind(world_position.xz);
-    let t = min(world_position.y / 0.1, 1.0);
+    // height above the instance, which sits on the terrain
+    let t = min((world_position.y - instance.model_matrix_3.y) / 0.1, 1.0);
     let age = pow(min(model.age, 1.0), 2.0);
     world_position.z += wind * age * t;
     
@@ -98,15 +138,204 @@ fn vs_main(
     out.age = model.age;
     out.world_pos = world_position.xyz;
     out.scale = scale;
+    out.origin = instance.model_matrix_3.xyz;
     
     return out;
 }
 
+// the terrain doesn't sway in the wind, its age is the signed distance to the closest path
+@vertex
+fn vs_floor(
+    model: VertexInput,
+    instance: InstanceInput
+) -> VertexOutput{
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * world_position;
+    out.normal = model.normal;
+    out.age = model.age;
+    out.world_pos = world_position.xyz;
+    out.scale = vec3<f32>(1.0);
+    out.origin = instance.model_matrix_3.xyz;
+
+    return out;
+}
+
+@vertex
+fn vs_grass(
+    model: VertexInput,
+    blade: GrassInput
+) -> VertexOutput{
+    // 0 at the root, 1 at the tip
+    let t = model.age;
+
+    let angle = blade.seed * 6.2831853;
+    let facing = vec3<f32>(cos(angle), 0.0, sin(angle));
+    let forward = vec3<f32>(-facing.z, 0.0, facing.x);
+
+    // the old quads were sheared by the wind at the tip, a blade bent into an arc leans over
+    // about half its angle, so the wind is turned into an angle that moves the tip as far
+    let wind = calculate_wind(blade.position.xz) * 2.0 / max(blade.height, 0.001);
+    let lean = forward.xz * blade.bend + vec2<f32>(0.0, wind);
+    let bend = length(lean);
+
+    // a blade keeps its length when it bends, each point moves along an arc
+    var along = 0.0;
+    var up = t;
+    var lean_dir = vec2<f32>(0.0, 1.0);
+    if bend > 0.0001 {
+        along = (1.0 - cos(bend * t)) / bend;
+        up = sin(bend * t) / bend;
+        lean_dir = lean / bend;
+    }
+
+    var world_position = vec4<f32>(blade.position, 1.0);
+    world_position += vec4<f32>(facing * model.position.x * blade.width, 0.0);
+    world_position += vec4<f32>(lean_dir.x, 0.0, lean_dir.y, 0.0) * along * blade.height;
+    world_position.y += up * blade.height;
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * world_position;
+    out.normal = forward;
+    out.age = t;
+    out.world_pos = world_position.xyz;
+    out.scale = vec3<f32>(blade.width, blade.height, 1.0);
+    out.origin = blade.position;
+    out.color_offset = blade.color_offset;
+
+    return out;
+}
+
+@vertex
+fn vs_particle(
+    model: VertexInput,
+    particle: ParticleInput
+) -> VertexOutput{
+    var local = model.position * particle.size;
+    // tilted around x first, then turned around y
+    let tc = cos(particle.tilt);
+    let ts = sin(particle.tilt);
+    local = vec3<f32>(local.x, local.y * tc - local.z * ts, local.y * ts + local.z * tc);
+
+    let c = cos(particle.rotation);
+    let s = sin(particle.rotation);
+    let offset = vec3<f32>(local.x * c + local.z * s, local.y, -local.x * s + local.z * c);
+    let world_position = vec4<f32>(particle.position + offset, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * world_position;
+    out.normal = vec3<f32>(s, 0.0, c);
+    out.age = particle.color;
+    out.world_pos = world_position.xyz;
+    out.scale = vec3<f32>(particle.size);
+    out.origin = particle.position;
+
+    return out;
+}
+
+// matches hash32 in noise.rs
+fn hash32(x: i32, z: i32, seed: u32) -> u32 {
+    var h = seed ^ (bitcast<u32>(x) * 0x27d4eb2du) ^ (bitcast<u32>(z) * 0x165667b1u);
+    h ^= h >> 15u;
+    h *= 0x2c1b3c6du;
+    h ^= h >> 12u;
+    h *= 0x297a2d39u;
+    h ^= h >> 15u;
+    return h;
+}
+
+fn unit32(h: u32) -> f32 {
+    return f32(h >> 8u) / 16777216.0;
+}
+
+// matches ShaderValue
+fn value_noise(p: vec2<f32>, seed: u32) -> f32 {
+    let cell = floor(p);
+    let f = p - cell;
+    let t = f * f * (3.0 - 2.0 * f);
+    let x = i32(cell.x);
+    let z = i32(cell.y);
+
+    let a = unit32(hash32(x, z, seed));
+    let b = unit32(hash32(x + 1, z, seed));
+    let c = unit32(hash32(x, z + 1, seed));
+    let d = unit32(hash32(x + 1, z + 1, seed));
+
+    return mix(mix(a, b, t.x), mix(c, d, t.x), t.y);
+}
+
+// matches GroundNoise
+fn ground_noise(p: vec2<f32>) -> f32 {
+    let seed = u32(render_data.noise_seed);
+    var frequency = 1.0;
+    var amplitude = 1.0;
+    var sum = 0.0;
+    var total = 0.0;
+    for (var octave = 0; octave < GROUND_OCTAVES; octave++) {
+        let offset = f32(octave) * 17.31;
+        let value = value_noise(vec2<f32>(p.x * frequency + offset, p.y * frequency - offset), seed);
+        sum += (value * 2.0 - 1.0) * amplitude;
+        total += amplitude;
+        frequency *= 2.0;
+        amplitude *= 0.5;
+    }
+    return clamp(sum / total * 0.5 + 0.5, 0.0, 1.0);
+}
+
+// matches Biomes::sample
+fn biome_weights(world_pos: vec3<f32>) -> vec4<f32> {
+    let value = ground_noise(world_pos.xz * render_data.biome_scale);
+
+    var weights = max(vec4<f32>(1.0) - abs(vec4<f32>(value) - render_data.biome_centers) / render_data.biome_blend, vec4<f32>(0.0));
+    weights = weights * weights;
+    // unused slots
+    weights *= step(vec4<f32>(0.5, 1.5, 2.5, 3.5), vec4<f32>(render_data.biome_count));
+
+    let total = dot(weights, vec4<f32>(1.0));
+    if total > 0.0 {
+        return weights / total;
+    }
+
+    var closest = 0;
+    for (var i = 1; i < i32(render_data.biome_count); i++) {
+        if abs(value - render_data.biome_centers[i]) < abs(value - render_data.biome_centers[closest]) {
+            closest = i;
+        }
+    }
+    var fallback = vec4<f32>(0.0);
+    fallback[closest] = 1.0;
+    return fallback;
+}
+
+// every biome has its own row in the lut
+fn sample_lut(t: f32, weights: vec4<f32>) -> vec3<f32> {
+    var color = vec3<f32>(0.0);
+    for (var i = 0; i < MAX_BIOMES; i++) {
+        let uv = vec2<f32>(t, (f32(i) + 0.5) / f32(MAX_BIOMES));
+        color += textureSampleLevel(lut_tex, lut_tex_sampler, uv, 0.0).rgb * weights[i];
+    }
+    return color;
+}
+
 fn sample_ground(world_pos: vec3<f32>) -> vec3<f32> {
     let uv = world_pos * 0.1;
     let ground = textureSample(tex, tex_sampler, uv.xz).r * 0.01;
-    let lut = textureSample(lut_tex, lut_tex_sampler, vec2<f32>(0.0, 0.5)).rgb;
-    return lut * (1.0 - ground);
+
+    let weights = biome_weights(world_pos);
+    var tint = vec3<f32>(0.0);
+    for (var i = 0; i < MAX_BIOMES; i++) {
+        tint += render_data.biome_tints[i].rgb * weights[i];
+    }
+
+    let lut = sample_lut(0.0, weights);
+    return lut * tint * (1.0 - ground);
 }
 
 fn get_shadow(normal: vec3<f32>) -> f32{
@@ -119,9 +348,9 @@ fn get_shadow(normal: vec3<f32>) -> f32{
 @fragment
 fn fs_color_object(in: VertexOutput) -> @location(0) vec4<f32>{
     var ground = sample_ground(in.world_pos);    
-    var t = clamp(in.world_pos.y / 0.1, 0.0, 1.0);
+    var t = clamp((in.world_pos.y - in.origin.y) / 0.1, 0.0, 1.0);
 
-    let lut = textureSample(lut_tex, lut_tex_sampler, vec2<f32>(in.age, 0.5)).rgb;
+    let lut = sample_lut(in.age, biome_weights(in.origin));
 
     let color = lut * t * get_shadow(in.normal) + vec3<f32>(ground * (1.0 - t));
 
@@ -129,27 +358,38 @@ fn fs_color_object(in: VertexOutput) -> @location(0) vec4<f32>{
 }
 
 @fragment
-fn fs_dust(in: VertexOutput) -> @location(0) vec4<f32>{
-    let t = 1.0 - in.scale.x / 0.01;
-    let color = textureSample(lut_tex, lut_tex_sampler, vec2<f32>(t, 0.5)).rgb;
-    // return vec4<f32>(vec3<f32>(in.scale.x/ 0.0085), 1.0);
+fn fs_particle(in: VertexOutput) -> @location(0) vec4<f32>{
+    let color = sample_lut(in.age, biome_weights(in.origin));
     return vec4<f32>(color, 1.0);
 }
 
+@fragment
+fn fs_creature(in: VertexOutput) -> @location(0) vec4<f32>{
+    let color = sample_lut(in.age, biome_weights(in.origin));
+    return vec4<f32>(color * get_shadow(in.normal), 1.0);
+}
+
 @fragment
 fn fs_floor(in: VertexOutput) -> @location(0) vec4<f32>{
     // let wind = calculate_wind(in.world_pos.xz);
     // return vec4<f32>(vec3<f32>(wind), 1.0);
 
-    return vec4<f32>(sample_ground(in.world_pos), 1.0);
+    let ground = sample_ground(in.world_pos);
+
+    // trodden dirt, with a bit of the ground texture showing through
+    let grain = textureSample(tex, tex_sampler, in.world_pos.xz * 0.4).r;
+    let path = PATH_COLOR * (0.85 + grain * 0.3);
+
+    // per fragment, the vertices are too far apart to carry the edge itself
+    let half_width = PATH_WIDTH * 0.5;
+    let mask = 1.0 - smoothstep(half_width, half_width + PATH_EDGE, abs(in.age));
+    return vec4<f32>(mix(ground, path, mask), 1.0);
 }
 
 @fragment
 fn fs_grass(in: VertexOutput) -> @location(0) vec4<f32>{
-    let ground = sample_ground(in.world_pos);
-    
-    var t = min(in.world_pos.y / 0.1, 1.0);
-    let color = textureSample(lut_tex, lut_tex_sampler, vec2<f32>(t, 0.5)).rgb;
+    let t = clamp(in.age + in.color_offset, 0.0, 1.0);
+    let color = sample_lut(t, biome_weights(in.origin));
 
     // return vec4<f32>(vec3<f32>(t), 1.0);
     return vec4<f32>(color, 1.0);
@@ -158,7 +398,7 @@ fn fs_grass(in: VertexOutput) -> @location(0) vec4<f32>{
 fn calculate_wind(coords: vec2<f32>) -> f32{
     let time = render_data.time * render_data.wind_speed;
 
-    let noise = textureSampleLevel(tex, tex_sampler, coords * render_data.wind_noise_scale + time * 0.01, 0.0).r;
+    let noise = ground_noise(coords * render_data.wind_noise_scale + time * 0.01 + WIND_NOISE_OFFSET);
 
     var pos = (coords.x + coords.y) + noise * render_data.wind_noise_strength;
     return sin(pos * render_data.wind_scale + time) * render_data.wind_strength;
@@ -0,0 +1,304 @@
+use std::{
+    cell::RefCell,
+    sync::{Arc, Mutex},
+};
+
+use jandering_engine::types::Vec2;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    application::{
+        particles::EmitterConfig,
+        placement::Placement,
+        presets::{parse_palette, PaletteStopJSON, Preset, PresetFile, PresetMeta},
+        species::Species,
+        ui::{ColorStops, UiBridge},
+    },
+    export::{self, ExportMesh, MeshFormat},
+    l_system::{
+        config::LConfig,
+        generator::{self, GeneratorKnobs},
+    },
+};
+
+lazy_static::lazy_static! {
+    pub static ref API_STATE: Arc<Mutex<ApiState>> = Arc::new(Mutex::new(ApiState::default()));
+}
+
+thread_local! {
+    static ERROR_CALLBACK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+    // export_mesh promises, resolved by the application on its next update
+    static MESH_REQUESTS: RefCell<Vec<(MeshFormat, js_sys::Function)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+// commands queued from js, picked up by the application on its next update
+#[derive(Default)]
+pub struct ApiState {
+    pub lsystem: Option<String>,
+    pub palette: Option<ColorStops>,
+    pub iterations: Option<u32>,
+    pub seed: Option<u64>,
+    pub regenerate: bool,
+    pub imported: Option<Preset>,
+    pub species_changes: Vec<SpeciesChange>,
+    pub placement: Option<Placement>,
+    pub emitter_changes: Vec<EmitterChange>,
+    pub camera_target: Option<Vec2>,
+
+    pub preset: Option<String>,
+    pub world_seed: u64,
+    // the cell the camera is looking at
+    pub position: (i32, i32),
+}
+
+pub enum SpeciesChange {
+    Add(Species),
+    Remove(String),
+    SetMainWeight(f32),
+}
+
+pub enum EmitterChange {
+    Add(String, EmitterConfig),
+    Remove(String),
+}
+
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct GardenApi;
+
+#[wasm_bindgen]
+impl GardenApi {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn set_lsystem(&self, json: String) {
+        API_STATE.lock().unwrap().lsystem = Some(json);
+    }
+
+    // stops are an array of { position: number, color: "#rrggbb" }
+    pub fn set_palette(&self, stops: JsValue) -> Result<(), JsValue> {
+        let json = js_sys::JSON::stringify(&stops)?
+            .as_string()
+            .unwrap_or_default();
+        let stops = serde_json::from_str::<Vec<PaletteStopJSON>>(&json)
+            .map_err(|err| err.to_string())
+            .and_then(|stops| parse_palette(&stops))
+            .map_err(|err| JsValue::from_str(&err))?;
+
+        API_STATE.lock().unwrap().palette = Some(stops);
+        Ok(())
+    }
+
+    pub fn set_iterations(&self, n: u32) {
+        API_STATE.lock().unwrap().iterations = Some(n);
+    }
+
+    // the same seed always grows the same plant in the same cell
+    pub fn set_seed(&self, n: u32) {
+        API_STATE.lock().unwrap().seed = Some(n as u64);
+    }
+
+    pub fn seed(&self) -> u32 {
+        API_STATE.lock().unwrap().world_seed as u32
+    }
+
+    // [x, z] of the spot the camera is looking at
+    pub fn position(&self) -> Vec<i32> {
+        let (x, z) = API_STATE.lock().unwrap().position;
+        vec![x, z]
+    }
+
+    // moves the camera so it looks at [x, z]
+    pub fn set_position(&self, x: f32, z: f32) {
+        API_STATE.lock().unwrap().camera_target = Some(Vec2::new(x, z));
+    }
+
+    // { type: "grid" | "jittered_grid" | "poisson_disk" | "noise_threshold" | "clustered", ... }
+    // with the strategy's own fields next to the type, plus an optional spacing
+    pub fn set_placement(&self, json: &str) -> Result<(), JsValue> {
+        let placement = serde_json::from_str::<Placement>(json)
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+        API_STATE.lock().unwrap().placement = Some(placement);
+        Ok(())
+    }
+
+    pub fn regenerate(&self) {
+        API_STATE.lock().unwrap().regenerate = true;
+    }
+
+    // format is "obj" or "ply", resolves to the file's bytes once the next frame gathers the plants
+    pub fn export_mesh(&self, format: &str) -> Result<js_sys::Promise, JsValue> {
+        let format = format.parse::<MeshFormat>().map_err(|err| {
+            report_error(&err);
+            JsValue::from_str(&err)
+        })?;
+        Ok(js_sys::Promise::new(&mut |resolve, _reject| {
+            MESH_REQUESTS.with(|requests| requests.borrow_mut().push((format, resolve)));
+        }))
+    }
+
+    // the current l-system, palette and iteration count as a preset file
+    pub fn export_preset(&self) -> Option<String> {
+        API_STATE.lock().unwrap().preset.clone()
+    }
+
+    pub fn import_preset(&self, json: &str) -> Result<(), JsValue> {
+        let preset = PresetFile::from_json(json).map_err(|err| {
+            report_error(&err);
+            JsValue::from_str(&err)
+        })?;
+        API_STATE.lock().unwrap().imported = Some(preset);
+        Ok(())
+    }
+
+    // generates a new grammar and switches to it, knobs go from 0 to 1
+    pub fn generate_lsystem(&self, seed: u32, bushiness: f32, symmetry: f32, height: f32) {
+        let knobs = GeneratorKnobs {
+            bushiness,
+            symmetry,
+            height,
+        };
+        let config = generator::generate(seed as u64, &knobs);
+        let preset = Preset {
+            meta: PresetMeta {
+                name: format!("generated {seed}"),
+                iterations: Some(config.rules.iterations),
+                ..Default::default()
+            },
+            text: config.to_json(),
+        };
+        API_STATE.lock().unwrap().imported = Some(preset);
+    }
+
+    // mixes another l-system into the world, replacing any species with the same name
+    pub fn add_species(&self, name: String, json: String, weight: f32) -> Result<(), JsValue> {
+        let config = LConfig::from_json(json).map_err(|err| {
+            report_error(&err);
+            JsValue::from_str(&err)
+        })?;
+        let species = Species {
+            name,
+            config,
+            weight,
+            generator: None,
+        };
+        API_STATE
+            .lock()
+            .unwrap()
+            .species_changes
+            .push(SpeciesChange::Add(species));
+        Ok(())
+    }
+
+    pub fn remove_species(&self, name: String) {
+        API_STATE
+            .lock()
+            .unwrap()
+            .species_changes
+            .push(SpeciesChange::Remove(name));
+    }
+
+    // how common the l-system from the editor is compared to the other species
+    pub fn set_main_species_weight(&self, weight: f32) {
+        API_STATE
+            .lock()
+            .unwrap()
+            .species_changes
+            .push(SpeciesChange::SetMainWeight(weight));
+    }
+
+    // adds a particle emitter, replacing any with the same name, the built in one is "dust"
+    pub fn add_emitter(&self, name: String, json: &str) -> Result<(), JsValue> {
+        let config = EmitterConfig::from_json(json).map_err(|err| {
+            report_error(&err);
+            JsValue::from_str(&err)
+        })?;
+        API_STATE
+            .lock()
+            .unwrap()
+            .emitter_changes
+            .push(EmitterChange::Add(name, config));
+        Ok(())
+    }
+
+    pub fn remove_emitter(&self, name: String) {
+        API_STATE
+            .lock()
+            .unwrap()
+            .emitter_changes
+            .push(EmitterChange::Remove(name));
+    }
+
+    pub fn on_error(&self, callback: js_sys::Function) {
+        ERROR_CALLBACK.with(|e| *e.borrow_mut() = Some(callback));
+    }
+}
+
+// `meshes` only runs when an export is waiting, copying every plant each frame would be a waste
+pub fn answer_mesh_requests(meshes: impl FnOnce() -> Vec<ExportMesh>) {
+    let requests = MESH_REQUESTS.with(|requests| requests.take());
+    if requests.is_empty() {
+        return;
+    }
+
+    let meshes = meshes();
+    for (format, resolve) in requests {
+        let data = export::export(&meshes, format);
+        let _ = resolve.call1(&JsValue::NULL, &js_sys::Uint8Array::from(data.as_slice()));
+    }
+}
+
+pub fn report_error(error: &str) {
+    ERROR_CALLBACK.with(|e| {
+        if let Some(callback) = e.borrow().as_ref() {
+            let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(error));
+        }
+    });
+}
+
+// settings set through the js api take priority over the wrapped ui
+pub struct ApiUi<T: UiBridge> {
+    inner: T,
+}
+
+impl<T: UiBridge> ApiUi<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T: UiBridge> UiBridge for ApiUi<T> {
+    fn read_lsystem(&mut self) -> Option<String> {
+        let imported = API_STATE.lock().unwrap().imported.take();
+        if let Some(preset) = imported {
+            self.inner.add_preset(preset);
+        }
+
+        let lsystem = API_STATE.lock().unwrap().lsystem.take();
+        lsystem.or_else(|| self.inner.read_lsystem())
+    }
+
+    fn read_iterations(&mut self) -> Option<u32> {
+        let iterations = API_STATE.lock().unwrap().iterations.take();
+        iterations.or_else(|| self.inner.read_iterations())
+    }
+
+    fn read_color_stops(&mut self) -> Option<ColorStops> {
+        let palette = API_STATE.lock().unwrap().palette.take();
+        palette.or_else(|| self.inner.read_color_stops())
+    }
+
+    fn display_error(&mut self, error: &str) {
+        if !error.is_empty() {
+            report_error(error);
+        }
+        self.inner.display_error(error);
+    }
+
+    fn add_preset(&mut self, preset: Preset) {
+        self.inner.add_preset(preset);
+    }
+}
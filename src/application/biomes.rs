@@ -0,0 +1,149 @@
+use jandering_engine::types::{Vec2, Vec3};
+
+use crate::{noise::Noise, render_data::RenderDataData};
+
+use super::{species::MAIN_SPECIES, ui::ColorStops};
+
+// has to match the size of the biome arrays in the shader
+pub const MAX_BIOMES: usize = 4;
+
+// world units are multiplied by this before sampling the ground noise
+const BIOME_SCALE: f32 = 0.05;
+// how far apart in noise value two biomes fully blend
+const BIOME_BLEND: f32 = 0.12;
+
+pub type BiomeWeights = [f32; MAX_BIOMES];
+
+pub struct Biome {
+    // the noise value this biome is strongest at
+    pub center: f32,
+    // species weight multipliers by name, species that aren't listed keep their weight
+    pub species: Vec<(String, f32)>,
+    // None uses the palette from the ui
+    pub palette: Option<ColorStops>,
+    pub grass_density: f32,
+    pub floor_tint: Vec3,
+}
+
+pub struct Biomes {
+    pub biomes: Vec<Biome>,
+}
+
+impl Default for Biomes {
+    fn default() -> Self {
+        let stop = |age: u32, r: f32, g: f32, b: f32| (age, Vec3::new(r, g, b));
+        Self {
+            biomes: vec![
+                // scrub, dry and mostly small generated species
+                Biome {
+                    center: 0.3,
+                    species: vec![(MAIN_SPECIES.to_string(), 0.3), ("shrub".to_string(), 1.5)],
+                    palette: Some(vec![
+                        stop(0, 0.16, 0.11, 0.07),
+                        stop(9, 0.45, 0.33, 0.2),
+                        stop(12, 0.93, 0.82, 0.6),
+                    ]),
+                    grass_density: 0.3,
+                    floor_tint: Vec3::new(1.05, 0.95, 0.85),
+                },
+                // meadow, looks like the garden always did
+                Biome {
+                    center: 0.5,
+                    species: Vec::new(),
+                    palette: None,
+                    grass_density: 1.0,
+                    floor_tint: Vec3::ONE,
+                },
+                // grove, mostly the main species
+                Biome {
+                    center: 0.7,
+                    species: vec![(MAIN_SPECIES.to_string(), 2.0), ("clump".to_string(), 0.5)],
+                    palette: None,
+                    grass_density: 0.6,
+                    floor_tint: Vec3::new(0.9, 1.0, 0.9),
+                },
+            ],
+        }
+    }
+}
+
+impl Biomes {
+    pub fn sample(&self, noise: &dyn Noise, pos: Vec2) -> BiomeWeights {
+        let pos = pos * BIOME_SCALE;
+        self.weights(noise.sample(pos.x, pos.y))
+    }
+
+    // matches biome_weights in the shader
+    pub fn weights(&self, value: f32) -> BiomeWeights {
+        let mut weights = [0.0; MAX_BIOMES];
+        for (weight, biome) in weights.iter_mut().zip(self.biomes.iter()) {
+            let t = (1.0 - (value - biome.center).abs() / BIOME_BLEND).max(0.0);
+            *weight = t * t;
+        }
+
+        let total = weights.iter().sum::<f32>();
+        if total > 0.0 {
+            weights.iter_mut().for_each(|e| *e /= total);
+        } else if let Some(closest) = self.closest(value) {
+            weights[closest] = 1.0;
+        }
+        weights
+    }
+
+    pub fn grass_density(&self, weights: &BiomeWeights) -> f32 {
+        self.blend(weights, |biome| biome.grass_density)
+    }
+
+    pub fn species_weight(&self, weights: &BiomeWeights, name: &str) -> f32 {
+        self.blend(weights, |biome| {
+            biome
+                .species
+                .iter()
+                .find(|(species, _)| species == name)
+                .map(|(_, weight)| *weight)
+                .unwrap_or(1.0)
+        })
+    }
+
+    // one palette per lut row, biomes without their own palette use the one from the ui
+    pub fn palettes(&self, main: &ColorStops) -> Vec<ColorStops> {
+        (0..MAX_BIOMES)
+            .map(|i| {
+                self.biomes
+                    .get(i)
+                    .and_then(|biome| biome.palette.clone())
+                    .unwrap_or_else(|| main.clone())
+            })
+            .collect()
+    }
+
+    pub fn write_render_data(&self, data: &mut RenderDataData) {
+        data.biome_scale = BIOME_SCALE;
+        data.biome_blend = BIOME_BLEND;
+        data.biome_count = self.biomes.len().min(MAX_BIOMES) as f32;
+        for (i, biome) in self.biomes.iter().take(MAX_BIOMES).enumerate() {
+            data.biome_centers[i] = biome.center;
+            data.biome_tints[i] = biome.floor_tint.extend(1.0).into();
+        }
+    }
+
+    fn blend(&self, weights: &BiomeWeights, f: impl Fn(&Biome) -> f32) -> f32 {
+        self.biomes
+            .iter()
+            .zip(weights.iter())
+            .map(|(biome, weight)| f(biome) * weight)
+            .sum()
+    }
+
+    fn closest(&self, value: f32) -> Option<usize> {
+        self.biomes
+            .iter()
+            .enumerate()
+            .min_by(|a, b| {
+                (a.1.center - value)
+                    .abs()
+                    .total_cmp(&(b.1.center - value).abs())
+            })
+            .map(|(i, _)| i)
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use jandering_engine::{
+    core::{
+        bind_group::camera::free::MatrixCameraBindGroup,
+        object::Instance,
+        renderer::{get_typed_bind_group, BindGroupHandle, Renderer},
+    },
+    types::{Mat4, Vec2, Vec3},
+};
+use rand::{rngs::StdRng, Rng};
+
+use crate::l_system::{
+    config::LConfig,
+    evolution::{crossover, mutate},
+};
+
+use super::{
+    chunks::Projection,
+    placement::PlantKey,
+    presets::{Preset, PresetMeta},
+    Application,
+};
+
+const MUTATION_RATE: f32 = 0.3;
+// picked plants are drawn this much bigger so they stand out from the rest
+const PICKED_SCALE: f32 = 1.2;
+
+// every cell grows its own grammar derived from the parents, picked cells become the next parents
+pub struct Breeding {
+    parents: Vec<LConfig>,
+    genomes: HashMap<PlantKey, LConfig>,
+    picked: HashMap<PlantKey, LConfig>,
+    generation: u32,
+}
+
+impl Breeding {
+    pub fn new(config: &LConfig) -> Self {
+        Self {
+            parents: vec![config.clone()],
+            genomes: HashMap::new(),
+            picked: HashMap::new(),
+            generation: 0,
+        }
+    }
+
+    pub fn genome(&mut self, pos: PlantKey, rng: &mut StdRng) -> &mut LConfig {
+        if !self.genomes.contains_key(&pos) {
+            let genome = self.derive(rng);
+            self.genomes.insert(pos, genome);
+        }
+        self.genomes.get_mut(&pos).unwrap()
+    }
+
+    pub fn forget(&mut self, pos: &PlantKey) {
+        self.genomes.remove(pos);
+    }
+
+    // returns whether the cell is picked now
+    pub fn toggle_pick(&mut self, pos: PlantKey) -> bool {
+        if self.picked.remove(&pos).is_some() {
+            return false;
+        }
+        match self.genomes.get(&pos) {
+            Some(genome) => {
+                self.picked.insert(pos, genome.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_picked(&self, pos: &PlantKey) -> bool {
+        self.picked.contains_key(pos)
+    }
+
+    pub fn next_generation(&mut self) -> bool {
+        if self.picked.is_empty() {
+            return false;
+        }
+        self.parents = self.picked.drain().map(|(_, genome)| genome).collect();
+        self.genomes.clear();
+        self.generation += 1;
+        true
+    }
+
+    pub fn favourite(&self) -> Option<&LConfig> {
+        self.picked.values().next()
+    }
+
+    fn derive(&self, rng: &mut StdRng) -> LConfig {
+        let child = if self.parents.len() > 1 {
+            let a = rng.gen_range(0..self.parents.len());
+            let b = (a + rng.gen_range(1..self.parents.len())) % self.parents.len();
+            crossover(&self.parents[a], &self.parents[b], rng)
+        } else {
+            self.parents[0].clone()
+        };
+        mutate(&child, rng, MUTATION_RATE)
+    }
+}
+
+impl Application {
+    pub fn toggle_breeding(&mut self) {
+        match self.breeding.take() {
+            Some(breeding) => {
+                if let Some(favourite) = breeding.favourite() {
+                    let mut config = favourite.clone();
+                    config
+                        .rules
+                        .set_iterations(self.settings.l_config.rules.iterations);
+                    self.settings.ui.add_preset(Preset {
+                        meta: PresetMeta {
+                            name: format!("bred {}", breeding.generation),
+                            ..Default::default()
+                        },
+                        text: config.to_json(),
+                    });
+                }
+                log::info!("stopped breeding");
+            }
+            None => {
+                self.breeding = Some(Breeding::new(&self.settings.l_config));
+                log::info!("started breeding, right click plants to pick them and N to breed");
+            }
+        }
+        self.clear_plants();
+    }
+
+    pub fn next_generation(&mut self) {
+        if let Some(breeding) = &mut self.breeding {
+            if breeding.next_generation() {
+                self.clear_plants();
+                log::info!("generation {}", breeding.generation);
+            }
+        }
+    }
+
+    pub fn pick_plant(&mut self, renderer: &mut dyn Renderer, screen_pos: Vec2) {
+        let Some(ground_pos) = screen_ray(renderer, self.camera, self.projection, screen_pos)
+            .and_then(|(origin, dir)| self.terrain.raycast(origin, dir))
+        else {
+            return;
+        };
+        let Some(breeding) = &mut self.breeding else {
+            return;
+        };
+        // the closest plant, as long as the click is roughly on it
+        let closest = self
+            .plants
+            .iter()
+            .map(|(key, obj)| {
+                let pos = obj.instances.first().unwrap().position();
+                (
+                    *key,
+                    Vec2::new(pos.x, pos.z).distance(Vec2::new(ground_pos.x, ground_pos.z)),
+                )
+            })
+            .filter(|(_, dist)| *dist < self.placement.spacing * 0.5)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((key, _)) = closest {
+            let picked = breeding.toggle_pick(key);
+            if let Some(obj) = self.plants.get_mut(&key) {
+                let position = obj.instances[0].position();
+                obj.instances[0] = plant_instance(position, picked);
+                obj.update(renderer);
+            }
+            log::info!(
+                "{} plant at {ground_pos}",
+                if picked { "picked" } else { "unpicked" }
+            );
+        }
+    }
+}
+
+// where a plant's mesh ends up, exports go through it too so they match what's on screen
+pub fn plant_transform(position: Vec3, picked: bool) -> Mat4 {
+    let scale = if picked { PICKED_SCALE } else { 1.0 };
+    Mat4::from_scale_translation(Vec3::splat(scale), position)
+}
+
+pub fn plant_instance(position: Vec3, picked: bool) -> Instance {
+    let mut instance = Instance::default();
+    instance.set_mat(plant_transform(position, picked));
+    instance
+}
+
+// the ray through a pixel, the same way Footprint casts the corners of the screen
+fn screen_ray(
+    renderer: &dyn Renderer,
+    camera: BindGroupHandle<MatrixCameraBindGroup>,
+    projection: Projection,
+    screen_pos: Vec2,
+) -> Option<(Vec3, Vec3)> {
+    let size = renderer.size();
+    let size = Vec2::new(size.x as f32, size.y as f32);
+    let aspect = size.x / size.y;
+
+    let camera = get_typed_bind_group(renderer, camera)?;
+    let dir = camera.direction();
+    let right = dir.cross(Vec3::Y).normalize_or_zero();
+    let right = if right == Vec3::ZERO { Vec3::X } else { right };
+    let up = right.cross(dir);
+
+    let ndc = Vec2::new(
+        screen_pos.x / size.x * 2.0 - 1.0,
+        1.0 - screen_pos.y / size.y * 2.0,
+    );
+    match projection {
+        Projection::Ortho { width, height } => {
+            let origin = camera.position()
+                + right * ndc.x * width * aspect * 0.5
+                + up * ndc.y * height * 0.5;
+            Some((origin, dir))
+        }
+        Projection::Perspective { fov } => {
+            let tan = (fov.to_radians() * 0.5).tan();
+            let ray = dir + right * ndc.x * tan * aspect + up * ndc.y * tan;
+            Some((camera.position(), ray.normalize()))
+        }
+    }
+}
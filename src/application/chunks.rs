@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+
+use jandering_engine::types::{Vec2, Vec3};
+
+use super::{logic::camera_ground_intersection, placement::CHUNK_SIZE};
+
+// chunks get loaded this far outside of what the camera sees
+const LOAD_MARGIN: f32 = 3.0;
+// and only unloaded once they're this far out, so wobbling on a border doesn't reload them
+const UNLOAD_MARGIN: f32 = 6.0;
+// the corners are cast against a flat floor, the margins cover the terrain's height
+// rays that miss the ground, or hit it very far away, stop here
+const MAX_VIEW_DISTANCE: f32 = 24.0;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Projection {
+    Ortho { width: f32, height: f32 },
+    Perspective { fov: f32 },
+}
+
+// what the camera sees of the ground, a convex quad
+pub struct Footprint {
+    corners: [Vec2; 4],
+}
+
+impl Footprint {
+    pub fn new(position: Vec3, direction: Vec3, projection: Projection, aspect: f32) -> Self {
+        // looking straight down there's no horizontal right, any flat axis will do
+        let right = direction.cross(Vec3::Y).normalize_or_zero();
+        let right = if right == Vec3::ZERO { Vec3::X } else { right };
+        let up = right.cross(direction);
+
+        let center = camera_ground_intersection(direction, position)
+            .map(|e| Vec2::new(e.x, e.z))
+            .unwrap_or(Vec2::new(position.x, position.z));
+
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| {
+            let (origin, dir) = match projection {
+                Projection::Ortho { width, height } => (
+                    position + right * x * width * aspect * 0.5 + up * y * height * 0.5,
+                    direction,
+                ),
+                Projection::Perspective { fov } => {
+                    let tan = (fov.to_radians() * 0.5).tan();
+                    let dir = direction + right * x * tan * aspect + up * y * tan;
+                    (position, dir.normalize())
+                }
+            };
+
+            let hit = camera_ground_intersection(dir, origin).map(|e| Vec2::new(e.x, e.z));
+            let towards = Vec2::new(dir.x, dir.z).normalize_or_zero();
+            match hit {
+                Some(hit) if hit.distance(center) <= MAX_VIEW_DISTANCE => hit,
+                _ => center + towards * MAX_VIEW_DISTANCE,
+            }
+        });
+
+        Self { corners }
+    }
+
+    // separating axis test between the quad grown by margin and a chunk
+    fn overlaps(&self, chunk: (i32, i32), margin: f32) -> bool {
+        let min = Vec2::new(chunk.0 as f32, chunk.1 as f32) * CHUNK_SIZE;
+        let max = min + Vec2::splat(CHUNK_SIZE);
+        let rect = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+
+        let edges = (0..4).map(|i| {
+            let edge = self.corners[(i + 1) % 4] - self.corners[i];
+            edge.perp().normalize_or_zero()
+        });
+        [Vec2::X, Vec2::Y].into_iter().chain(edges).all(|axis| {
+            let (quad_min, quad_max) = project(&self.corners, axis);
+            let (rect_min, rect_max) = project(&rect, axis);
+            quad_min - margin <= rect_max && rect_min <= quad_max + margin
+        })
+    }
+
+    fn bounds(&self, margin: f32) -> ((i32, i32), (i32, i32)) {
+        let min = self.corners.iter().fold(Vec2::MAX, |acc, e| acc.min(*e)) - margin;
+        let max = self.corners.iter().fold(Vec2::MIN, |acc, e| acc.max(*e)) + margin;
+        let min = (min / CHUNK_SIZE).floor();
+        let max = (max / CHUNK_SIZE).floor();
+        ((min.x as i32, min.y as i32), (max.x as i32, max.y as i32))
+    }
+}
+
+fn project(points: &[Vec2; 4], axis: Vec2) -> (f32, f32) {
+    points
+        .iter()
+        .map(|e| e.dot(axis))
+        .fold((f32::MAX, f32::MIN), |(min, max), e| {
+            (min.min(e), max.max(e))
+        })
+}
+
+#[derive(Default)]
+pub struct ChunkChanges {
+    pub loaded: Vec<(i32, i32)>,
+    pub unloaded: Vec<(i32, i32)>,
+    // still loaded, only their plants have to be built again
+    pub reloaded: Vec<(i32, i32)>,
+}
+
+#[derive(Default)]
+pub struct ChunkManager {
+    loaded: HashSet<(i32, i32)>,
+    reload: bool,
+    reload_all: bool,
+}
+
+impl ChunkManager {
+    pub fn update(&mut self, footprint: &Footprint) -> ChunkChanges {
+        let unloaded = self
+            .loaded
+            .iter()
+            .filter(|chunk| !footprint.overlaps(**chunk, UNLOAD_MARGIN))
+            .copied()
+            .collect::<Vec<_>>();
+        unloaded.iter().for_each(|chunk| {
+            self.loaded.remove(chunk);
+        });
+
+        let (min, max) = footprint.bounds(LOAD_MARGIN);
+        let reload = std::mem::take(&mut self.reload);
+        let reload_all = std::mem::take(&mut self.reload_all);
+        let still_loaded = || self.loaded.iter().copied().collect::<Vec<_>>();
+        // reloading everything already rebuilds the plants
+        let reloaded = if reload && !reload_all {
+            still_loaded()
+        } else {
+            Vec::new()
+        };
+        let mut loaded = if reload_all {
+            still_loaded()
+        } else {
+            Vec::new()
+        };
+        for x in min.0..=max.0 {
+            for z in min.1..=max.1 {
+                let chunk = (x, z);
+                if !self.loaded.contains(&chunk) && footprint.overlaps(chunk, LOAD_MARGIN) {
+                    self.loaded.insert(chunk);
+                    loaded.push(chunk);
+                }
+            }
+        }
+
+        ChunkChanges {
+            loaded,
+            unloaded,
+            reloaded,
+        }
+    }
+
+    pub fn is_loaded(&self, chunk: (i32, i32)) -> bool {
+        self.loaded.contains(&chunk)
+    }
+
+    // the next update reports every chunk that stays loaded as reloaded, for their plants
+    pub fn reload(&mut self) {
+        self.reload = true;
+    }
+
+    // the next update reports every chunk that stays loaded as newly loaded too, so everything
+    // on them is built again
+    pub fn reload_all(&mut self) {
+        self.reload_all = true;
+    }
+}
@@ -0,0 +1,362 @@
+use std::f32::consts::TAU;
+
+use jandering_engine::{
+    core::{object::Instance, renderer::Renderer},
+    types::{Mat4, Qua, Vec2, Vec3},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    color_obj::{AgeObject, AgeVertex},
+    icosphere,
+};
+
+use super::placement::{creature_seed, CHUNK_SIZE};
+
+// creatures that wander further than this from the middle of their chunk turn back
+const LEASH: f32 = CHUNK_SIZE;
+// how quickly the velocity follows the steering, per second
+const STEERING: f32 = 3.0;
+// radians per second the wander direction drifts by at most
+const WANDER_JITTER: f32 = 4.0;
+const TRUNK_AVOID_RADIUS: f32 = 0.25;
+const FLOCK_RADIUS: f32 = 0.8;
+const SEPARATION_RADIUS: f32 = 0.15;
+// chance per second that a flying bird looks for a branch to sit on
+const PERCH_CHANCE: f32 = 0.05;
+const PERCH_RANGE: f32 = 3.0;
+// seconds a bird sits on a branch, min and max
+const PERCH_TIME: (f32, f32) = (3.0, 8.0);
+// a landing bird snaps onto the branch once it's this close
+const LANDING_DISTANCE: f32 = 0.03;
+// flaps per second
+const FLAP_SPEED: f32 = 12.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreatureKind {
+    // crawls around on the ground
+    Bug,
+    // flies around in flocks and sits on branch tips
+    Bird,
+}
+
+impl CreatureKind {
+    fn speed(&self) -> f32 {
+        match self {
+            CreatureKind::Bug => 0.08,
+            CreatureKind::Bird => 0.9,
+        }
+    }
+
+    fn size(&self) -> f32 {
+        match self {
+            CreatureKind::Bug => 0.012,
+            CreatureKind::Bird => 0.03,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Activity {
+    Wandering,
+    // flying to a branch tip
+    Landing(Vec3),
+    Perched { time_left: f32 },
+}
+
+struct Creature {
+    kind: CreatureKind,
+    home: (i32, i32),
+    position: Vec3,
+    velocity: Vec3,
+    wander: f32,
+    // birds keep this far above the terrain
+    altitude: f32,
+    // kept while standing still so nothing snaps back to facing forward
+    heading: f32,
+    activity: Activity,
+}
+
+impl Creature {
+    fn home_center(&self) -> Vec2 {
+        (Vec2::new(self.home.0 as f32, self.home.1 as f32) + 0.5) * CHUNK_SIZE
+    }
+
+    fn flat_position(&self) -> Vec2 {
+        Vec2::new(self.position.x, self.position.z)
+    }
+}
+
+// small animals, spawned per chunk and gone again once their chunk is unloaded
+pub struct Creatures {
+    creatures: Vec<Creature>,
+    bugs: AgeObject,
+    birds: AgeObject,
+    // plant positions to steer around and branch tips to sit on, in world space
+    trunks: Vec<Vec2>,
+    perches: Vec<Vec3>,
+    time: f32,
+}
+
+impl Creatures {
+    pub fn new(renderer: &mut dyn Renderer) -> Self {
+        let (vertices, indices) = bug_data();
+        let bugs = AgeObject::new(renderer, vertices, indices, Vec::new());
+        let (vertices, indices) = bird_data();
+        let birds = AgeObject::new(renderer, vertices, indices, Vec::new());
+
+        Self {
+            creatures: Vec::new(),
+            bugs,
+            birds,
+            trunks: Vec::new(),
+            perches: Vec::new(),
+            time: 0.0,
+        }
+    }
+
+    pub fn set_plants(&mut self, trunks: Vec<Vec2>, perches: Vec<Vec3>) {
+        self.trunks = trunks;
+        self.perches = perches;
+        // the branch a bird was headed for or sitting on may be gone
+        for creature in self.creatures.iter_mut() {
+            let perch = match creature.activity {
+                Activity::Wandering => continue,
+                Activity::Landing(perch) => perch,
+                Activity::Perched { .. } => creature.position,
+            };
+            if !self.perches.contains(&perch) {
+                creature.activity = Activity::Wandering;
+            }
+        }
+    }
+
+    // the same chunk and seed always get the same creatures
+    pub fn load(&mut self, chunk: (i32, i32), seed: u64, height: impl Fn(Vec2) -> f32) {
+        self.unload(&chunk);
+
+        let mut rng = StdRng::seed_from_u64(creature_seed(seed, chunk));
+        let origin = Vec2::new(chunk.0 as f32, chunk.1 as f32) * CHUNK_SIZE;
+        let mut spawn = |kind: CreatureKind, pos: Vec2, altitude: f32, rng: &mut StdRng| {
+            let wander = rng.gen_range(0.0..TAU);
+            self.creatures.push(Creature {
+                kind,
+                home: chunk,
+                position: Vec3::new(pos.x, height(pos) + altitude, pos.y),
+                velocity: Vec3::ZERO,
+                wander,
+                altitude,
+                heading: wander,
+                activity: Activity::Wandering,
+            });
+        };
+
+        for _ in 0..rng.gen_range(2..6) {
+            let pos = origin + Vec2::new(rng.gen(), rng.gen()) * CHUNK_SIZE;
+            spawn(CreatureKind::Bug, pos, 0.0, &mut rng);
+        }
+
+        // about every other chunk has a flock
+        if rng.gen_bool(0.5) {
+            let center = origin + Vec2::new(rng.gen(), rng.gen()) * CHUNK_SIZE;
+            for _ in 0..rng.gen_range(3..8) {
+                let pos = center + Vec2::new(rng.gen(), rng.gen()) - 0.5;
+                let altitude = rng.gen_range(0.8..1.4);
+                spawn(CreatureKind::Bird, pos, altitude, &mut rng);
+            }
+        }
+    }
+
+    pub fn unload(&mut self, chunk: &(i32, i32)) {
+        self.creatures.retain(|e| e.home != *chunk);
+    }
+
+    pub fn update(
+        &mut self,
+        renderer: &mut dyn Renderer,
+        dt: f32,
+        height: impl Fn(Vec2) -> f32,
+        rng: &mut StdRng,
+    ) {
+        self.time += dt;
+
+        // flocking looks at where everyone was at the start of the frame
+        let birds = self
+            .creatures
+            .iter()
+            .filter(|e| e.kind == CreatureKind::Bird && matches!(e.activity, Activity::Wandering))
+            .map(|e| (e.position, e.velocity))
+            .collect::<Vec<_>>();
+
+        for creature in self.creatures.iter_mut() {
+            let speed = creature.kind.speed();
+            let desired = match creature.activity {
+                Activity::Wandering => {
+                    creature.wander += rng.gen_range(-1.0..1.0) * WANDER_JITTER * dt;
+                    let wander = Vec2::from_angle(creature.wander);
+
+                    let mut steer = wander + avoid_trunks(creature.flat_position(), &self.trunks);
+                    let home = creature.home_center() - creature.flat_position();
+                    if home.length() > LEASH {
+                        steer += home.normalize();
+                    }
+                    let mut desired = Vec3::new(steer.x, 0.0, steer.y).normalize_or_zero() * speed;
+
+                    if creature.kind == CreatureKind::Bird {
+                        desired += flock(creature.position, creature.velocity, &birds) * speed;
+                        let target = height(creature.flat_position()) + creature.altitude;
+                        desired.y = (target - creature.position.y).clamp(-1.0, 1.0) * speed;
+
+                        if rng.gen::<f32>() < PERCH_CHANCE * dt {
+                            if let Some(perch) = closest(creature.position, &self.perches) {
+                                creature.activity = Activity::Landing(perch);
+                            }
+                        }
+                    }
+                    desired
+                }
+                Activity::Landing(perch) => {
+                    let to = perch - creature.position;
+                    if to.length() < LANDING_DISTANCE {
+                        creature.position = perch;
+                        creature.velocity = Vec3::ZERO;
+                        creature.activity = Activity::Perched {
+                            time_left: rng.gen_range(PERCH_TIME.0..PERCH_TIME.1),
+                        };
+                        continue;
+                    }
+                    // slows down for the last bit so it doesn't overshoot
+                    to.normalize() * speed * (to.length() / 0.3).min(1.0)
+                }
+                Activity::Perched { time_left } => {
+                    let time_left = time_left - dt;
+                    creature.activity = if time_left > 0.0 {
+                        Activity::Perched { time_left }
+                    } else {
+                        creature.wander = creature.heading;
+                        Activity::Wandering
+                    };
+                    continue;
+                }
+            };
+
+            creature.velocity = creature
+                .velocity
+                .lerp(desired, (STEERING * dt).min(1.0))
+                .clamp_length_max(speed);
+            creature.position += creature.velocity * dt;
+
+            if creature.kind == CreatureKind::Bug {
+                creature.position.y = height(creature.flat_position());
+            }
+
+            let flat = Vec2::new(creature.velocity.x, creature.velocity.z);
+            if flat.length_squared() > 1e-6 {
+                creature.heading = flat.x.atan2(flat.y);
+            }
+        }
+
+        let time = self.time;
+        let instances = |kind: CreatureKind| {
+            self.creatures
+                .iter()
+                .filter(|e| e.kind == kind)
+                .map(|e| {
+                    let mut scale = Vec3::splat(kind.size());
+                    if kind == CreatureKind::Bird && !matches!(e.activity, Activity::Perched { .. })
+                    {
+                        scale.x *= 0.6 + (time * FLAP_SPEED + e.wander).sin().abs() * 0.4;
+                    }
+                    let rotation = Qua::from_axis_angle(Vec3::Y, e.heading);
+                    let mut instance = Instance::default();
+                    instance.set_mat(Mat4::from_scale_rotation_translation(
+                        scale, rotation, e.position,
+                    ));
+                    instance
+                })
+                .collect::<Vec<_>>()
+        };
+        let bugs = instances(CreatureKind::Bug);
+        let birds = instances(CreatureKind::Bird);
+        self.bugs.instances = bugs;
+        self.birds.instances = birds;
+        self.bugs.update(renderer);
+        self.birds.update(renderer);
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = &AgeObject> {
+        [&self.bugs, &self.birds].into_iter()
+    }
+}
+
+// pushes away from nearby trunks, harder the closer they are
+fn avoid_trunks(pos: Vec2, trunks: &[Vec2]) -> Vec2 {
+    trunks
+        .iter()
+        .filter_map(|trunk| {
+            let away = pos - *trunk;
+            let dist = away.length();
+            (dist < TRUNK_AVOID_RADIUS && dist > 0.0)
+                .then(|| away / dist * (1.0 - dist / TRUNK_AVOID_RADIUS) * 4.0)
+        })
+        .sum()
+}
+
+// separation, alignment and cohesion with the birds around
+fn flock(pos: Vec3, velocity: Vec3, birds: &[(Vec3, Vec3)]) -> Vec3 {
+    let mut separation = Vec3::ZERO;
+    let mut heading = Vec3::ZERO;
+    let mut center = Vec3::ZERO;
+    let mut count = 0;
+    for (other, other_velocity) in birds {
+        let dist = pos.distance(*other);
+        if dist == 0.0 || dist > FLOCK_RADIUS {
+            continue;
+        }
+        if dist < SEPARATION_RADIUS {
+            separation += (pos - *other) / dist * (1.0 - dist / SEPARATION_RADIUS);
+        }
+        heading += *other_velocity;
+        center += *other;
+        count += 1;
+    }
+    if count == 0 {
+        return Vec3::ZERO;
+    }
+
+    let alignment = (heading / count as f32 - velocity).normalize_or_zero();
+    let cohesion = (center / count as f32 - pos).normalize_or_zero();
+    separation * 2.0 + alignment * 0.5 + cohesion * 0.3
+}
+
+fn closest(pos: Vec3, points: &[Vec3]) -> Option<Vec3> {
+    points
+        .iter()
+        .filter(|e| e.distance(pos) < PERCH_RANGE)
+        .min_by(|a, b| a.distance(pos).total_cmp(&b.distance(pos)))
+        .copied()
+}
+
+// a squashed ball, unit sized
+fn bug_data() -> (Vec<AgeVertex>, Vec<u32>) {
+    let mat = Mat4::from_scale(Vec3::new(0.6, 0.4, 1.0));
+    icosphere::generate(0.1, mat, 0)
+}
+
+// two flat wings meeting along the body, nose towards +z, unit sized
+fn bird_data() -> (Vec<AgeVertex>, Vec<u32>) {
+    let vertex = |x: f32, y: f32, z: f32| AgeVertex {
+        position: Vec3::new(x, y, z),
+        normal: Vec3::Y,
+        age: 0.5,
+        ..Default::default()
+    };
+    let vertices = vec![
+        vertex(0.0, 0.0, 0.5),
+        vertex(-1.0, 0.2, -0.3),
+        vertex(0.0, 0.0, -0.5),
+        vertex(1.0, 0.2, -0.3),
+    ];
+    let indices = vec![0, 1, 2, 0, 2, 3];
+    (vertices, indices)
+}
@@ -1,35 +1,54 @@
-use std::collections::HashMap;
-
 use jandering_engine::{
-    core::{
-        object::Instance,
-        renderer::{get_typed_bind_group, Renderer},
-    },
+    core::renderer::{get_typed_bind_group, get_typed_bind_group_mut, Renderer},
     types::{Mat4, Qua, Vec2, Vec3},
-    utils::load_text,
 };
-use rand::{rngs::ThreadRng, Rng};
-use wasm_bindgen::JsCast;
-use web_sys::{HtmlDivElement, HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::HashSet;
 
 use crate::{
+    api::{answer_mesh_requests, EmitterChange, SpeciesChange, API_STATE},
     color_obj::{AgeObject, AgeVertex},
+    export::ExportMesh,
+    grass::GrassInstance,
     icosphere,
-    image::Image,
     l_system::{self, config::LConfig, RenderShape},
+    noise::{GroundNoise, Noise},
+    storage,
 };
 
-use super::{cylinder, Application};
-
-const DUST_SCALE: Vec3 = Vec3::splat(0.0085);
-
-const N_PLANTS: u32 = 4;
-const PLANT_SPACING: i32 = 3;
+use super::{
+    biomes::Biomes,
+    breeding::{plant_instance, plant_transform},
+    chunks::{ChunkChanges, Footprint},
+    cylinder,
+    particles::wind,
+    paths::Paths,
+    placement::{grass_seed, plant_seed, PlantKey, CHUNK_SIZE},
+    presets::{PresetFile, USER_PRESET_KEY, USER_PRESET_NAME},
+    scatter::{rejection_sample, smoothstep, Exclusion},
+    setup::create_lut_textures,
+    terrain::Terrain,
+    Application,
+};
 
-const GRASS_RANGE: f32 = 2.75;
-const GRASS_ITERATIONS: u32 = 12;
+// blades tried per square unit of a chunk, the density decides how many of them grow
+const GRASS_PER_UNIT: f32 = 120.0;
+// tries per blade to find a spot the density allows
+const GRASS_ATTEMPTS: u32 = 8;
+// no grass grows this close to a plant
+const TRUNK_EXCLUSION_RADIUS: f32 = 0.15;
 const GRASS_HEIGHT: f32 = 0.1;
 const GRASS_WIDTH: f32 = 0.0075;
+// radians
+const GRASS_MAX_BEND: f32 = 0.6;
+// blades lean further where the ground grain the floor is speckled with is rough, the grain is
+// treated as a heightmap this tall and the roughness scaled by this many radians
+const GRAIN_HEIGHT: f32 = 0.05;
+const GRAIN_BEND: f32 = 2.0;
+const GRASS_COLOR_VARIATION: f32 = 0.1;
+// world units are multiplied by these before sampling the ground noise
+const GRASS_CLUMP_FREQUENCY: f32 = 0.5;
+const GRASS_SIZE_FREQUENCY: f32 = 1.5;
 
 lazy_static::lazy_static! {
     static ref CYLINDER_DATA: (Vec<AgeVertex>, Vec<u32>) = gen_cylinder_data();
@@ -58,66 +77,283 @@ fn cylinder(age: f32, next_age: f32, mat: Mat4, index_offset: u32) -> (Vec<AgeVe
     (vertices, indices)
 }
 
+// a plant's mesh, with the tips of its branches relative to its root
+pub struct PlantMesh {
+    pub vertices: Vec<AgeVertex>,
+    pub indices: Vec<u32>,
+    pub tips: Vec<Vec3>,
+}
+
 impl Application {
-    pub fn update_config(&mut self) {
-        if let Some(config) = self.read_lsystem() {
-            match LConfig::from_json(config) {
-                Ok(l_config) => {
-                    self.l_config = l_config;
-                    self.plants.clear();
-                    self.display_error("");
-                }
-                Err(error) => {
-                    self.display_error(&error);
-                }
-            }
+    // everything the settings ui changed since the last frame
+    pub fn update_settings(&mut self, renderer: &mut dyn Renderer) {
+        let changes = self.settings.update();
+        if changes.l_system {
+            self.clear_plants();
+        }
+        if changes.color_stops {
+            create_lut_textures(
+                renderer,
+                &self.biomes.palettes(&self.settings.color_stops),
+                Some(self.lut_texture),
+                Some(self.lut_texture_linear),
+                Some(self.lut_sampler),
+            );
+        }
+        if changes.any() {
+            self.save_preset();
         }
     }
+
     pub fn spawn_new_plants(&mut self, renderer: &mut dyn Renderer) {
         let camera = get_typed_bind_group(renderer, self.camera).unwrap();
-        if let Some(ground_pos) = camera_ground_intersection(camera.direction(), camera.position())
-        {
-            let snapped_cam = (ground_pos / PLANT_SPACING as f32).round() * PLANT_SPACING as f32;
+        if let Some(ground_pos) = self.terrain.raycast(camera.position(), camera.direction()) {
+            API_STATE.lock().unwrap().position =
+                (ground_pos.x.round() as i32, ground_pos.z.round() as i32);
+        }
 
-            let half = N_PLANTS as i32 / 2;
-            self.plants.retain(|_, obj| {
-                let half = (half * PLANT_SPACING) as f32;
-                let pos = obj.instances.first().unwrap().position();
-                (pos.x - snapped_cam.x).abs() <= half && (pos.z - snapped_cam.z).abs() <= half
+        let aspect = {
+            let size = renderer.size();
+            size.x as f32 / size.y as f32
+        };
+        let footprint = Footprint::new(
+            camera.position(),
+            camera.direction(),
+            self.projection,
+            aspect,
+        );
+        let changes = self.chunk_manager.update(&footprint);
+
+        for chunk in changes.unloaded.iter() {
+            self.terrain.unload(chunk);
+        }
+        for chunk in changes.loaded.iter() {
+            self.terrain.load(renderer, *chunk, &self.paths);
+        }
+        self.update_grass(renderer, &changes);
+        for chunk in changes.unloaded.iter() {
+            self.creatures.unload(chunk);
+        }
+        for chunk in changes.loaded.iter() {
+            let terrain = &self.terrain;
+            self.creatures
+                .load(*chunk, self.seed, |pos| terrain.height(pos));
+        }
+
+        let unloaded = changes.unloaded.into_iter().collect::<HashSet<_>>();
+        let evicted = self
+            .plants
+            .keys()
+            .filter(|(chunk, _)| unloaded.contains(chunk))
+            .copied()
+            .collect::<Vec<_>>();
+        let mut changed = !evicted.is_empty();
+        for key in evicted {
+            let obj = self.plants.remove(&key).unwrap();
+            let tips = self.plant_tips.remove(&key).unwrap_or_default();
+            // bred plants get a fresh genome when they come back, so their meshes aren't kept
+            match &mut self.breeding {
+                Some(breeding) => breeding.forget(&key),
+                None => self.plant_cache.insert(
+                    key,
+                    PlantMesh {
+                        vertices: obj.vertices,
+                        indices: obj.indices,
+                        tips,
+                    },
+                ),
+            }
+        }
+
+        for chunk in changes.loaded.into_iter().chain(changes.reloaded) {
+            for (i, pos) in self.plant_positions(chunk).to_vec() {
+                let key = (chunk, i);
+                let cached = match self.breeding {
+                    Some(_) => None,
+                    None => self.plant_cache.take(&key),
+                };
+                let mesh = match cached {
+                    Some(mesh) => mesh,
+                    None => self.build_plant(key, pos),
+                };
+
+                self.plant_tips.insert(key, mesh.tips);
+                let object = AgeObject::new(
+                    renderer,
+                    mesh.vertices,
+                    mesh.indices,
+                    vec![plant_instance(
+                        Vec3::new(pos.x, self.terrain.height(pos), pos.y),
+                        self.breeding
+                            .as_ref()
+                            .is_some_and(|breeding| breeding.is_picked(&key)),
+                    )],
+                );
+                self.plants.insert(key, object);
+                changed = true;
+            }
+        }
+
+        if !unloaded.is_empty() {
+            let chunk_manager = &self.chunk_manager;
+            self.plant_spots.retain(|chunk, _| {
+                (-1..=1)
+                    .any(|x| (-1..=1).any(|z| chunk_manager.is_loaded((chunk.0 + x, chunk.1 + z))))
             });
+        }
 
-            for x in -half..half {
-                for z in -half..half {
-                    let pos = (
-                        snapped_cam.x as i32 + x * PLANT_SPACING,
-                        snapped_cam.z as i32 + z * PLANT_SPACING,
-                    );
-
-                    #[allow(clippy::map_entry)]
-                    if !self.plants.contains_key(&pos) {
-                        let (vertices, indices) = self.new_plant(&mut self.rng.clone());
-
-                        let object = AgeObject::new(
-                            renderer,
-                            vertices,
-                            indices,
-                            vec![Instance::default().translate(Vec3::new(
-                                pos.0 as f32,
-                                0.0,
-                                pos.1 as f32,
-                            ))],
-                        );
-                        self.plants.insert(pos, object);
-                    }
+        if changed {
+            self.update_plant_points();
+        }
+        answer_mesh_requests(|| self.export_meshes());
+    }
+
+    // indexed by where they were placed, so plants keep their keys whatever the paths skip
+    fn plant_positions(&mut self, chunk: (i32, i32)) -> &[(usize, Vec2)] {
+        let (placement, paths) = (&self.placement, &self.paths);
+        let (seed, ground_noise) = (self.seed, &self.ground_noise);
+        self.plant_spots.entry(chunk).or_insert_with(|| {
+            placement
+                .place(chunk, seed, ground_noise)
+                .into_iter()
+                .enumerate()
+                .filter(|(_, pos)| !paths.blocks_plant(*pos))
+                .collect()
+        })
+    }
+
+    fn build_plant(&mut self, key: PlantKey, pos: Vec2) -> PlantMesh {
+        let mut rng = StdRng::seed_from_u64(plant_seed(self.seed, key));
+        let cell = (pos / self.placement.spacing.max(1.0)).floor();
+        let biome = self.biomes.sample(&self.ground_noise, pos);
+        let species = self.species.pick((cell.x as i32, cell.y as i32), |name| {
+            self.biomes.species_weight(&biome, name)
+        });
+        let config = match (&mut self.breeding, species) {
+            (Some(breeding), _) => breeding.genome(key, &mut rng),
+            (None, Some(species)) => self.species.config_mut(species),
+            (None, None) => &mut self.settings.l_config,
+        };
+        Self::new_plant(config, &mut rng)
+    }
+
+    // for when the plants need to be rebuilt, cached meshes are just as stale
+    pub fn clear_plants(&mut self) {
+        self.plants.clear();
+        self.plant_tips.clear();
+        self.chunk_manager.reload();
+        self.plant_cache.clear();
+        self.update_plant_points();
+    }
+
+    // for when the seed or the placement changed, which the grass, terrain and creatures depend
+    // on as well
+    pub fn clear_world(&mut self) {
+        self.plant_spots.clear();
+        self.clear_plants();
+        self.chunk_manager.reload_all();
+    }
+
+    // leaves fall from the tips of every loaded plant, creatures sit on them and steer around
+    // the trunks
+    fn update_plant_points(&mut self) {
+        let tips = self
+            .plant_tips
+            .iter()
+            .filter_map(|(key, tips)| {
+                let origin = self.plants.get(key)?.instances.first()?.position();
+                Some(tips.iter().map(move |tip| origin + *tip))
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        let trunks = self
+            .plants
+            .values()
+            .map(|obj| {
+                let pos = obj.instances.first().unwrap().position();
+                Vec2::new(pos.x, pos.z)
+            })
+            .collect();
+        self.creatures.set_plants(trunks, tips.clone());
+        self.particles.set_sources(tips);
+    }
+
+    fn export_meshes(&self) -> Vec<ExportMesh> {
+        self.plants
+            .iter()
+            .map(|(key, obj)| {
+                let picked = self
+                    .breeding
+                    .as_ref()
+                    .is_some_and(|breeding| breeding.is_picked(key));
+                ExportMesh {
+                    transform: plant_transform(obj.instances.first().unwrap().position(), picked),
+                    vertices: obj.vertices.clone(),
+                    indices: obj.indices.clone(),
                 }
+            })
+            .collect()
+    }
+
+    pub fn update_api(&mut self, renderer: &mut dyn Renderer) {
+        let mut state = API_STATE.lock().unwrap();
+        if let Some(seed) = state.seed.take() {
+            self.seed = seed;
+            self.species.reseed(seed);
+            self.terrain = Terrain::new(seed);
+            self.paths = Paths::new(seed);
+            self.ground_noise = GroundNoise::new(seed);
+            self.clear_world();
+            log::info!("world seed {seed}");
+        }
+        state.world_seed = self.seed;
+        if std::mem::take(&mut state.regenerate) {
+            self.clear_plants();
+        }
+        if let Some(placement) = state.placement.take() {
+            self.placement = placement;
+            self.clear_world();
+        }
+        if let Some(target) = state.camera_target.take() {
+            self.look_at(renderer, target);
+        }
+        for change in state.species_changes.drain(..) {
+            match change {
+                SpeciesChange::Add(species) => self.species.add(species),
+                SpeciesChange::Remove(name) => self.species.remove(&name),
+                SpeciesChange::SetMainWeight(weight) => self.species.main_weight = weight,
+            }
+            self.clear_plants();
+        }
+        for change in state.emitter_changes.drain(..) {
+            match change {
+                EmitterChange::Add(name, config) => self.particles.add(renderer, name, config),
+                EmitterChange::Remove(name) => self.particles.remove(&name),
             }
         }
     }
 
-    pub fn new_plant(&mut self, rng: &mut ThreadRng) -> (Vec<AgeVertex>, Vec<u32>) {
+    // slides the camera along the ground until it looks at target, keeping its height and angle
+    fn look_at(&mut self, renderer: &mut dyn Renderer, target: Vec2) {
+        let camera = get_typed_bind_group_mut(renderer, self.camera).unwrap();
+        let (position, direction) = (camera.position(), camera.direction());
+        let Some(looking_at) = self
+            .terrain
+            .raycast(position, direction)
+            .or_else(|| camera_ground_intersection(direction, position))
+        else {
+            return;
+        };
+
+        let target = Vec3::new(target.x, self.terrain.height(target), target.y);
+        *camera.position_mut() = position + target - looking_at;
+    }
+
+    pub fn new_plant(config: &mut LConfig, rng: &mut StdRng) -> PlantMesh {
         // let timer = Timer::now("building took: ".to_string());
-        self.l_config.randomize_rule_sets(None, rng);
-        let shapes = l_system::build(&self.l_config, rng);
+        config.randomize_rule_sets(None, rng);
+        let shapes = l_system::build(config, rng);
+        let tips = shape_tips(&shapes);
 
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
@@ -135,206 +371,147 @@ impl Application {
 
         // timer.print();
 
-        (vertices, indices)
+        PlantMesh {
+            vertices,
+            indices,
+            tips,
+        }
     }
 
-    pub fn update_dust(&mut self, dt: f32, renderer: &mut dyn Renderer) {
+    pub fn update_particles(&mut self, dt: f32, renderer: &mut dyn Renderer) {
         let camera = get_typed_bind_group(renderer, self.camera).unwrap();
-        let ground_pos =
-            camera_ground_intersection(camera.direction(), camera.position()).unwrap_or(Vec3::ZERO);
+        let ground_pos = self
+            .terrain
+            .raycast(camera.position(), camera.direction())
+            .unwrap_or(Vec3::ZERO);
         let ground_pos = Vec2::new(ground_pos.x, ground_pos.z);
 
-        let idle_rotation = Qua::from_axis_angle(Vec3::Y, 3.0 * dt);
-
-        for dust in self.dust.instances.iter_mut() {
-            let mat = dust.mat();
-            let (mut scale, mut rotation, mut pos) = mat.to_scale_rotation_translation();
-            let mut pos_2d = Vec2::new(pos.x, pos.z);
-            if pos_2d.distance(ground_pos) > 7.0 || scale.x < 0.0 {
-                let dist = self.rng.gen_range(0.0f32..7.0f32);
-                let angle = self.rng.gen_range(0.0f32..360.0f32);
-
-                let offset = Vec2::from_angle(angle.to_radians()) * dist;
-                pos_2d = ground_pos + offset;
-                pos.y = self.rng.gen_range(-0.5..0.0);
-                scale = DUST_SCALE;
-
-                let angle = self.rng.gen_range(0.0f32..360.0f32);
-                rotation *= Qua::from_axis_angle(Vec3::Y, angle);
-            }
-
-            rotation *= idle_rotation;
-            pos.x = pos_2d.x;
-            pos.y += 0.1 * dt;
-            pos.z = pos_2d.y;
-
-            scale -= DUST_SCALE.x * dt * 0.2;
-
-            let mat = Mat4::from_scale_rotation_translation(scale, rotation, pos);
-            dust.set_mat(mat);
-        }
-
-        self.dust.update(renderer);
+        let render_data = get_typed_bind_group(renderer, self.render_data)
+            .unwrap()
+            .data;
+        self.particles.update(
+            renderer,
+            dt,
+            ground_pos,
+            &self.terrain,
+            |pos| wind(&render_data, &self.ground_noise, pos),
+            &mut self.rng,
+        );
     }
 
-    pub fn update_grass(&mut self, renderer: &mut dyn Renderer) {
-        let camera = get_typed_bind_group(renderer, self.camera).unwrap();
-        let ground_pos =
-            camera_ground_intersection(camera.direction(), camera.position()).unwrap_or(Vec3::ZERO);
-        let ground_pos = Vec2::new(ground_pos.x, ground_pos.z);
-
-        for grass in self.grass.instances.iter_mut() {
-            let mat = grass.mat();
-            let (_, rotation, mut pos) = mat.to_scale_rotation_translation();
-            let mut pos_2d = Vec2::new(pos.x, pos.z);
-            if pos_2d.distance(ground_pos) > GRASS_RANGE {
-                let dist = self.rng.gen_range(0.9f32..1.0f32);
-                let angle = self.rng.gen_range(0.0f32..360.0f32);
-
-                let offset = Vec2::from_angle(angle.to_radians()) * dist * GRASS_RANGE;
-                pos_2d = ground_pos + offset;
-
-                let scale_mod = 0.7 + self.noise_image.sample(pos_2d.x, pos_2d.y) * 0.6;
-                let mut scale = Vec3::new(GRASS_WIDTH, GRASS_HEIGHT, 1.0) * scale_mod;
-                pos.x = pos_2d.x;
-                pos.z = pos_2d.y;
-
-                pos = Self::place_pos_on_heightmap(
-                    pos,
-                    GRASS_ITERATIONS,
-                    &self.noise_image,
-                    &mut self.rng,
-                );
-                pos.y = 0.0;
-                if (Vec3::ZERO).distance(pos) < 3.0 {
-                    scale *= 0.01;
-                }
+    pub fn update_creatures(&mut self, dt: f32, renderer: &mut dyn Renderer) {
+        let terrain = &self.terrain;
+        self.creatures
+            .update(renderer, dt, |pos| terrain.height(pos), &mut self.rng);
+    }
 
-                let mat = Mat4::from_scale_rotation_translation(scale, rotation, pos);
-                grass.set_mat(mat);
-            }
+    // grass is kept per chunk and streamed along with the terrain
+    fn update_grass(&mut self, renderer: &mut dyn Renderer, changes: &ChunkChanges) {
+        for chunk in changes.unloaded.iter() {
+            self.grass_chunks.remove(chunk);
+        }
+        for chunk in changes.loaded.iter() {
+            let blades = self.grass_chunk(*chunk);
+            self.grass_chunks.insert(*chunk, blades);
         }
 
-        self.grass.update(renderer);
-    }
-
-    pub fn place_pos_on_heightmap(
-        mut pos: Vec3,
-        iterations: u32,
-        heightmap: &Image,
-        rng: &mut ThreadRng,
-    ) -> Vec3 {
-        for _ in 0..=iterations {
-            let mut highest_val = heightmap.sample(pos.x, pos.z);
-            for i in -1..1 {
-                for j in -1..1 {
-                    let this_pos = pos + Vec3::new(j as f32 * 0.01, 0.0, i as f32 * 0.01);
-                    let val = heightmap.sample(this_pos.x, this_pos.z);
-                    if val > highest_val {
-                        highest_val = val;
-                        pos = this_pos;
-                    }
-                }
-            }
+        if !changes.loaded.is_empty() || !changes.unloaded.is_empty() {
+            self.grass.instances = self.grass_chunks.values().flatten().copied().collect();
+            self.grass.update(renderer);
         }
-        pos + Vec3::new(
-            rng.gen_range(-0.05..=0.05),
-            0.0,
-            rng.gen_range(-0.05..=0.05),
-        )
     }
 
-    pub fn update_iteration_count(&mut self) {
-        if let Some(value) = web_sys::window()
-            .and_then(|win| win.document())
-            .and_then(|doc| doc.get_element_by_id("detail"))
-            .and_then(|el| el.dyn_into::<HtmlInputElement>().ok())
-            .map(|el| el.value())
-            .and_then(|value| value.parse::<u32>().ok())
-        {
-            if self.l_config.rules.iterations != value {
-                self.plants.clear();
-                self.l_config.rules.iterations = value;
-            }
+    // the same chunk and seed always grow the same blades
+    fn grass_chunk(&mut self, chunk: (i32, i32)) -> Vec<GrassInstance> {
+        let mut rng = StdRng::seed_from_u64(grass_seed(self.seed, chunk));
+        let origin = Vec2::new(chunk.0 as f32, chunk.1 as f32) * CHUNK_SIZE;
+        let exclusions = self.trunk_exclusions(chunk);
+        let count = (CHUNK_SIZE * CHUNK_SIZE * GRASS_PER_UNIT) as usize;
+
+        let mut blades = Vec::new();
+        for _ in 0..count {
+            let Some(pos) = rejection_sample(
+                &mut rng,
+                GRASS_ATTEMPTS,
+                |rng| {
+                    origin
+                        + Vec2::new(
+                            rng.gen_range(0.0..CHUNK_SIZE),
+                            rng.gen_range(0.0..CHUNK_SIZE),
+                        )
+                },
+                |pos| grass_density(&self.biomes, &self.ground_noise, &self.paths, pos),
+                &exclusions,
+            ) else {
+                continue;
+            };
+
+            let size = pos * GRASS_SIZE_FREQUENCY;
+            let scale_mod = 0.7 + self.ground_noise.sample(size.x, size.y) * 0.6;
+            let roughness = 1.0 - self.noise_image.normal(pos.x, pos.y, GRAIN_HEIGHT).y;
+            blades.push(GrassInstance {
+                position: Vec3::new(pos.x, self.terrain.height(pos), pos.y),
+                height: GRASS_HEIGHT * scale_mod * rng.gen_range(0.8..1.2),
+                width: GRASS_WIDTH * scale_mod,
+                bend: rng.gen_range(0.0..GRASS_MAX_BEND) + roughness * GRAIN_BEND,
+                color_offset: rng.gen_range(-GRASS_COLOR_VARIATION..GRASS_COLOR_VARIATION),
+                seed: rng.gen(),
+            });
         }
+        blades
     }
 
-    fn read_lsystem(&self) -> Option<String> {
-        let window = web_sys::window()?;
-        let document = window.document()?;
-        let presets_input = document
-            .get_element_by_id("presets")?
-            .dyn_into::<HtmlSelectElement>()
-            .ok()?;
-        let presets_edit = document
-            .get_element_by_id("edit-text-input")?
-            .dyn_into::<HtmlTextAreaElement>()
-            .ok()?;
-
-        if presets_input.has_attribute("changed") {
-            if let Some(preset_text) = self.presets.get(&presets_input.value()) {
-                presets_edit.set_value(preset_text);
-                let _ = presets_edit.set_attribute("changed", "");
+    // from where the plants get placed rather than the loaded plants, so it doesn't matter which
+    // neighbours happen to be loaded, plants near the border may belong to either side
+    fn trunk_exclusions(&mut self, chunk: (i32, i32)) -> Vec<Exclusion> {
+        let min = Vec2::new(chunk.0 as f32, chunk.1 as f32) * CHUNK_SIZE
+            - Vec2::splat(TRUNK_EXCLUSION_RADIUS);
+        let max = min + Vec2::splat(CHUNK_SIZE + TRUNK_EXCLUSION_RADIUS * 2.0);
+
+        let mut exclusions = Vec::new();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                let neighbour = (chunk.0 + x, chunk.1 + z);
+                exclusions.extend(
+                    self.plant_positions(neighbour)
+                        .iter()
+                        .map(|(_, pos)| *pos)
+                        .filter(|pos| pos.cmpge(min).all() && pos.cmple(max).all())
+                        .map(|center| Exclusion {
+                            center,
+                            radius: TRUNK_EXCLUSION_RADIUS,
+                        }),
+                );
             }
-            let _ = presets_input.remove_attribute("changed");
-        }
-
-        if presets_edit.has_attribute("changed") {
-            let _ = presets_edit.remove_attribute("changed");
-            Some(presets_edit.value())
-        } else {
-            None
         }
+        exclusions
     }
 
-    fn display_error(&self, string: &str) -> Option<()> {
-        let window = web_sys::window()?;
-        let document = window.document()?;
-        let error_box = document
-            .get_element_by_id("error-box")?
-            .dyn_into::<HtmlDivElement>()
-            .ok()?;
-        error_box.set_inner_text(string);
-        Some(())
-    }
-}
-
-pub fn read_lut(linear: bool) -> Option<Vec<Vec3>> {
-    let elements = web_sys::window()?
-        .document()?
-        .get_elements_by_class_name("color-stop");
-    let mut colors = Vec::with_capacity(elements.length() as usize);
-    for i in 0..elements.length() {
-        let element = elements.get_with_index(i)?;
-        if element.id() == "color-stop-template" {
-            continue;
+    // keeps the current state around for the next session and for exporting
+    fn save_preset(&self) {
+        let preset = PresetFile::new(
+            USER_PRESET_NAME,
+            &self.settings.l_config,
+            &self.settings.color_stops,
+        )
+        .to_json();
+        if let Err(err) = storage::save(USER_PRESET_KEY, &preset) {
+            log::warn!("couldn't save the preset: {err}");
         }
-        let color = element
-            .children()
-            .get_with_index(1)?
-            .dyn_into::<HtmlInputElement>()
-            .unwrap();
-        let age = element
-            .last_element_child()?
-            .dyn_into::<HtmlInputElement>()
-            .unwrap();
-        let age = age.value().parse::<u32>().unwrap_or(0);
-        let color = hex_color::HexColor::parse(&color.value())
-            .map(|e| Vec3::new(e.r as f32 / 255.0, e.g as f32 / 255.0, e.b as f32 / 255.0))
-            .unwrap_or(Vec3::ZERO);
-        colors.push((age, color));
+        API_STATE.lock().unwrap().preset = Some(preset);
     }
+}
 
-    let colors = if linear {
-        l_system::colors::parse_colors_linear(&colors)
-    } else {
-        l_system::colors::parse_colors(&colors)
-    };
-
-    Some(colors)
+// grass gathers where the noise is high, thinned out by the biome, and doesn't grow on paths
+fn grass_density(biomes: &Biomes, noise: &dyn Noise, paths: &Paths, pos: Vec2) -> f32 {
+    let biome = biomes.sample(noise, pos);
+    let clump = pos * GRASS_CLUMP_FREQUENCY;
+    let clumps = smoothstep(0.3, 0.7, noise.sample(clump.x, clump.y));
+    biomes.grass_density(&biome) * clumps * (1.0 - paths.mask(pos))
 }
 
-fn camera_ground_intersection(dir: Vec3, cam_pos: Vec3) -> Option<Vec3> {
+// against a flat floor at y = 0, the terrain is in Terrain::raycast
+pub fn camera_ground_intersection(dir: Vec3, cam_pos: Vec3) -> Option<Vec3> {
     let denom = Vec3::Y.dot(-dir);
     if denom > 1e-6 {
         let dif = -cam_pos;
@@ -345,6 +522,32 @@ fn camera_ground_intersection(dir: Vec3, cam_pos: Vec3) -> Option<Vec3> {
     }
 }
 
+// ends of lines nothing else grows out of, and every circle
+fn shape_tips(shapes: &[RenderShape]) -> Vec<Vec3> {
+    // positions are compared to a thousandth, the turtle doesn't land exactly on the same spot
+    let snap = |pos: Vec3| {
+        let pos = (pos * 1000.0).round();
+        (pos.x as i32, pos.y as i32, pos.z as i32)
+    };
+
+    let starts = shapes
+        .iter()
+        .filter_map(|shape| match shape {
+            RenderShape::Line { start, .. } => Some(snap(*start)),
+            RenderShape::Circle { .. } => None,
+        })
+        .collect::<HashSet<_>>();
+
+    shapes
+        .iter()
+        .filter_map(|shape| match shape {
+            RenderShape::Line { end, .. } if !starts.contains(&snap(*end)) => Some(*end),
+            RenderShape::Line { .. } => None,
+            RenderShape::Circle { pos, .. } => Some(*pos),
+        })
+        .collect()
+}
+
 fn shape_to_mesh_data(shape: RenderShape, vertices_len: u32) -> (Vec<AgeVertex>, Vec<u32>) {
     let (vertices, indices) = match shape {
         RenderShape::Line {
@@ -373,38 +576,3 @@ fn shape_to_mesh_data(shape: RenderShape, vertices_len: u32) -> (Vec<AgeVertex>,
     };
     (vertices, indices)
 }
-
-pub async fn setups_js_inputs() -> Option<HashMap<String, String>> {
-    let window = web_sys::window()?;
-    let document = window.document()?;
-    let presets_input = document
-        .get_element_by_id("presets")?
-        .dyn_into::<HtmlSelectElement>()
-        .ok()?;
-
-    let presets = load_text(jandering_engine::utils::FilePath::FileName("presets.json"))
-        .await
-        .unwrap();
-    let presets: Vec<String> = serde_json::from_str(&presets).unwrap();
-    if presets.is_empty() {
-        return None;
-    }
-
-    let mut map = HashMap::new();
-
-    presets_input.set_value(&presets[0]);
-    let _ = presets_input.set_attribute("changed", "");
-    for preset in presets {
-        let el = document.create_element("option").ok()?;
-        el.set_inner_html(&preset);
-        let _ = presets_input.append_child(&el);
-
-        let text = load_text(jandering_engine::utils::FilePath::FileName(&format!(
-            "systems/{preset}.json"
-        )))
-        .await
-        .unwrap();
-        map.insert(preset, text);
-    }
-    Some(map)
-}
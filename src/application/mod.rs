@@ -7,43 +7,70 @@ use jandering_engine::{
         },
         engine::{Engine, EngineContext},
         event_handler::EventHandler,
-        object::{Instance, Object, Renderable, Vertex},
+        object::{Instance, Renderable, Vertex},
         renderer::{
             create_typed_bind_group, get_typed_bind_group, get_typed_bind_group_mut,
             BindGroupHandle, Renderer, SamplerHandle, ShaderHandle, TextureHandle,
         },
         shader::ShaderDescriptor,
         texture::{TextureDescriptor, TextureFormat},
-        window::{Key, WindowEvent},
+        window::{InputState, Key, MouseButton, WindowEvent},
     },
-    types::Vec2,
+    types::{Vec2, Vec3},
     utils::load_text,
 };
-use rand::{rngs::ThreadRng, thread_rng};
+use rand::{rngs::StdRng, SeedableRng};
 use std::{
+    cell::Cell,
     collections::HashMap,
     sync::{Arc, Mutex},
 };
 
 use crate::{
-    color_obj::AgeObject, cylinder, image::Image, l_system::config::LConfig,
-    render_data::RenderDataBindGroup,
+    api::ApiUi, color_obj::AgeObject, cylinder, grass::GrassInstance, image::Image,
+    noise::GroundNoise, render_data::RenderDataBindGroup,
 };
 
 use self::{
-    logic::setups_js_inputs,
-    setup::{create_camera, create_lut_textures, create_objects, create_shaders, create_textures},
+    biomes::Biomes,
+    breeding::Breeding,
+    chunks::{ChunkManager, Projection},
+    creatures::Creatures,
+    particles::Particles,
+    paths::Paths,
+    placement::{Placement, PlantKey},
+    plant_cache::{PlantCache, PLANT_CACHE_BYTES},
+    presets::load_presets,
+    settings::Settings,
+    setup::{create_camera, create_objects, create_shaders, create_textures},
+    species::SpeciesRegistry,
+    terrain::Terrain,
+    ui::{MemoryUi, UiBridge, WebUi},
 };
 
+pub mod biomes;
+pub mod breeding;
+pub mod chunks;
+pub mod creatures;
 pub mod logic;
+pub mod particles;
+pub mod paths;
+pub mod placement;
+pub mod plant_cache;
+pub mod presets;
+pub mod scatter;
+pub mod settings;
 pub mod setup;
+pub mod species;
+pub mod terrain;
+pub mod ui;
 
 lazy_static::lazy_static! {
     #[derive(Debug)]
     pub static ref SHADER_CODE_MUTEX: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
 }
 
-type Plants = HashMap<(i32, i32), AgeObject>;
+type Plants = HashMap<PlantKey, AgeObject>;
 
 pub struct Application {
     last_time: web_time::Instant,
@@ -53,17 +80,34 @@ pub struct Application {
     grass_shader: ShaderHandle,
     camera: BindGroupHandle<MatrixCameraBindGroup>,
     camera_controller: Box<dyn CameraController>,
+    projection: Projection,
     depth_texture: TextureHandle,
 
     plants: Plants,
-    l_config: LConfig,
-    presets: HashMap<String, String>,
-    floor: Object<Instance>,
-
-    dust: AgeObject,
-    dust_shader: ShaderHandle,
-    grass: AgeObject,
+    // branch tips of every loaded plant, relative to its root
+    plant_tips: HashMap<PlantKey, Vec<Vec3>>,
+    chunk_manager: ChunkManager,
+    placement: Placement,
+    // where plants go in each chunk, kept for loaded chunks and their neighbours since the grass
+    // needs them too
+    plant_spots: HashMap<(i32, i32), Vec<(usize, Vec2)>>,
+    plant_cache: PlantCache,
+    settings: Settings,
+    species: SpeciesRegistry,
+    biomes: Biomes,
+    terrain: Terrain,
+    paths: Paths,
+
+    particles: Particles,
+    particle_shader: ShaderHandle,
+    creatures: Creatures,
+    creature_shader: ShaderHandle,
+    grass: AgeObject<GrassInstance>,
+    grass_chunks: HashMap<(i32, i32), Vec<GrassInstance>>,
+    // the ground grain the floor shader speckles with, everything picked by location uses
+    // ground_noise instead
     noise_image: Image,
+    ground_noise: GroundNoise,
     noise_texture: BindGroupHandle<TextureBindGroup>,
 
     lut_texture: BindGroupHandle<TextureBindGroup>,
@@ -72,27 +116,49 @@ pub struct Application {
 
     render_data: BindGroupHandle<RenderDataBindGroup>,
 
-    rng: ThreadRng,
+    rng: StdRng,
+    seed: u64,
+
+    breeding: Option<Breeding>,
+    mouse_position: Vec2,
 
     randomize_rule_sets_timer: f32,
 }
 
-const N_DUST: u32 = 60;
-const N_GRASS: u32 = 5000;
-
 const REFERENCE_DIAGONAL: f32 = 2202.0;
 const ORTHO_WIDTH: f32 = 2.0;
 const ORTHO_HEIGHT: f32 = ORTHO_WIDTH;
 const ORTHO_NEAR: f32 = 0.003;
 const ORTHO_FAR: f32 = 1000.0;
+const ORTHO_PROJECTION: Projection = Projection::Ortho {
+    width: ORTHO_WIDTH,
+    height: ORTHO_HEIGHT,
+};
+const PERSPECTIVE_FOV: f32 = 35.0;
 
 const RANDOMIZE_RULE_SETS_TIME_SECS: f32 = 10.0;
 
 impl Application {
     pub async fn new(engine: &mut Engine) -> Self {
-        let (shader, floor_shader, grass_shader, dust_shader) =
+        if cfg!(target_arch = "wasm32") {
+            Self::with_ui(engine, Box::new(ApiUi::new(WebUi::new().await))).await
+        } else {
+            // there's no settings panel natively, start from the first preset and let the api
+            // drive the rest
+            let mut ui = MemoryUi::default();
+            if let Some(preset) = load_presets().await.into_iter().next() {
+                ui.add_preset(preset);
+            }
+            Self::with_ui(engine, Box::new(ApiUi::new(ui))).await
+        }
+    }
+
+    pub async fn with_ui(engine: &mut Engine, ui: Box<dyn UiBridge>) -> Self {
+        let (shader, floor_shader, grass_shader, particle_shader, creature_shader) =
             create_shaders(engine.renderer.as_mut()).await;
 
+        let settings = Settings::new(ui);
+        let biomes = Biomes::default();
         let (
             depth_texture,
             noise_image,
@@ -100,20 +166,24 @@ impl Application {
             lut_sampler,
             lut_texture,
             lut_texture_linear,
-        ) = create_textures(engine.renderer.as_mut()).await;
+        ) = create_textures(
+            engine.renderer.as_mut(),
+            &biomes.palettes(&settings.color_stops),
+        )
+        .await;
 
-        let (plants, floor, dust, grass) = create_objects(engine.renderer.as_mut());
-
-        let l_config = LConfig::default();
-
-        let presets = setups_js_inputs().await.unwrap_or(HashMap::new());
+        let (plants, grass) = create_objects(engine.renderer.as_mut());
+        let particles = Particles::new(engine.renderer.as_mut());
+        let creatures = Creatures::new(engine.renderer.as_mut());
 
         let render_data = RenderDataBindGroup::new(engine.renderer.as_mut());
         let render_data = create_typed_bind_group(engine.renderer.as_mut(), render_data);
 
         let camera = create_camera(engine.renderer.as_mut());
 
-        let rng = thread_rng();
+        let rng = StdRng::from_entropy();
+        let seed = rand::random::<u32>() as u64;
+        log::info!("world seed {seed}");
 
         Self {
             last_time: web_time::Instant::now(),
@@ -121,20 +191,32 @@ impl Application {
             shader,
             camera,
             camera_controller: Box::<FreeCameraController>::default(),
+            projection: ORTHO_PROJECTION,
             depth_texture,
 
             grass_shader,
             floor_shader,
 
             plants,
-            l_config,
-            presets,
-            floor,
-
-            dust,
-            dust_shader,
+            plant_tips: HashMap::new(),
+            chunk_manager: ChunkManager::default(),
+            placement: Placement::default(),
+            plant_spots: HashMap::new(),
+            plant_cache: PlantCache::new(PLANT_CACHE_BYTES),
+            settings,
+            species: SpeciesRegistry::new(seed),
+            biomes,
+            terrain: Terrain::new(seed),
+            paths: Paths::new(seed),
+
+            particles,
+            particle_shader,
+            creatures,
+            creature_shader,
             grass,
+            grass_chunks: HashMap::new(),
             noise_image,
+            ground_noise: GroundNoise::new(seed),
             noise_texture,
 
             lut_texture,
@@ -144,6 +226,10 @@ impl Application {
             render_data,
 
             rng,
+            seed,
+
+            breeding: None,
+            mouse_position: Vec2::ZERO,
 
             randomize_rule_sets_timer: RANDOMIZE_RULE_SETS_TIME_SECS,
         }
@@ -195,7 +281,10 @@ impl EventHandler for Application {
                 camera.controller.as_mut().unwrap(),
                 &mut self.camera_controller,
             );
-            camera.make_perspective(35.0, aspect, 0.01, 10000.0);
+            camera.make_perspective(PERSPECTIVE_FOV, aspect, 0.01, 10000.0);
+            self.projection = Projection::Perspective {
+                fov: PERSPECTIVE_FOV,
+            };
         }
 
         if context.events.is_pressed(Key::G) {
@@ -217,6 +306,7 @@ impl EventHandler for Application {
                 ORTHO_NEAR,
                 ORTHO_FAR,
             );
+            self.projection = ORTHO_PROJECTION;
         }
 
         if context
@@ -236,6 +326,7 @@ impl EventHandler for Application {
                 ORTHO_NEAR,
                 ORTHO_FAR,
             );
+            self.projection = ORTHO_PROJECTION;
 
             context.renderer.re_create_texture(
                 TextureDescriptor {
@@ -247,34 +338,58 @@ impl EventHandler for Application {
             );
         }
 
+        let mouse_position = Cell::new(None);
+        let right_clicked = context.events.matches(|e| match e {
+            WindowEvent::MouseMotion(position) => {
+                mouse_position.set(Some(Vec2::from(*position)));
+                false
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Right,
+                state,
+            } => matches!(state, InputState::Pressed),
+            _ => false,
+        });
+        if let Some(mouse_position) = mouse_position.get() {
+            self.mouse_position = mouse_position;
+        }
+
+        if context.events.is_pressed(Key::B) {
+            self.toggle_breeding();
+        }
+
+        if context.events.is_pressed(Key::N) {
+            self.next_generation();
+        }
+
+        if right_clicked {
+            self.pick_plant(context.renderer.as_mut(), self.mouse_position);
+        }
+
         let camera = get_typed_bind_group_mut(context.renderer.as_mut(), self.camera).unwrap();
         camera.update(context.events, dt);
 
         self.randomize_rule_sets_timer -= dt;
         if self.randomize_rule_sets_timer < 0.0 {
-            self.l_config.randomize_rule_sets(Some(1), &mut self.rng);
+            self.settings
+                .l_config
+                .randomize_rule_sets(Some(1), &mut self.rng);
             self.randomize_rule_sets_timer = RANDOMIZE_RULE_SETS_TIME_SECS;
             log::info!("randomized rule sets");
         }
 
-        self.update_config();
+        self.update_api(context.renderer.as_mut());
+        self.update_settings(context.renderer.as_mut());
         self.spawn_new_plants(context.renderer.as_mut());
-        self.update_dust(dt, context.renderer.as_mut());
-        self.update_grass(context.renderer.as_mut());
-
-        create_lut_textures(
-            context.renderer.as_mut(),
-            Some(self.lut_texture),
-            Some(self.lut_texture_linear),
-            Some(self.lut_sampler),
-        );
-
-        self.update_iteration_count();
+        self.update_particles(dt, context.renderer.as_mut());
+        self.update_creatures(dt, context.renderer.as_mut());
 
         let render_data =
             get_typed_bind_group_mut(context.renderer.as_mut(), self.render_data).unwrap();
         render_data.data.time = self.time;
+        render_data.data.noise_seed = self.ground_noise.shader_seed();
         render_data.data.wind_strength = 0.002 + (self.time * 0.2).sin().powf(4.0).max(0.0) * 0.01;
+        self.biomes.write_render_data(&mut render_data.data);
     }
 
     fn on_render(&mut self, renderer: &mut Box<dyn Renderer>) {
@@ -289,6 +404,21 @@ impl EventHandler for Application {
             .values()
             .map(|e| e as &dyn Renderable)
             .collect::<Vec<_>>();
+        let creatures = self
+            .creatures
+            .objects()
+            .map(|e| e as &dyn Renderable)
+            .collect::<Vec<_>>();
+        let particles = self
+            .particles
+            .objects()
+            .map(|e| e as &dyn Renderable)
+            .collect::<Vec<_>>();
+        let terrain = self
+            .terrain
+            .objects()
+            .map(|e| e as &dyn Renderable)
+            .collect::<Vec<_>>();
 
         renderer
             .new_pass()
@@ -299,11 +429,13 @@ impl EventHandler for Application {
             .bind(1, self.render_data.into())
             .bind(2, self.noise_texture.into())
             .bind(3, self.lut_texture.into())
-            .render(&[&self.floor])
+            .render(&terrain)
             .set_shader(self.shader)
             .render(&plants)
-            .set_shader(self.dust_shader)
-            .render(&[&self.dust])
+            .set_shader(self.particle_shader)
+            .render(&particles)
+            .set_shader(self.creature_shader)
+            .render(&creatures)
             .bind(3, self.lut_texture_linear.into())
             .set_shader(self.grass_shader)
             .render(&[&self.grass])
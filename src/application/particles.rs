@@ -0,0 +1,427 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use jandering_engine::{
+    core::{
+        renderer::Renderer,
+        shader::{
+            BufferLayout, BufferLayoutEntry, BufferLayoutEntryDataType, BufferLayoutStepMode,
+        },
+    },
+    types::{Vec2, Vec3},
+};
+use rand::{rngs::StdRng, Rng};
+use serde::Deserialize;
+
+use crate::{color_obj::AgeObject, noise::Noise, render_data::RenderDataData};
+
+use super::terrain::Terrain;
+
+const DUST_JSON: &str = include_str!("../../res/emitters/dust.json");
+const LEAVES_JSON: &str = include_str!("../../res/emitters/leaves.json");
+pub const DUST_EMITTER: &str = "dust";
+pub const LEAVES_EMITTER: &str = "leaves";
+// matches WIND_NOISE_OFFSET in the shader
+const WIND_NOISE_OFFSET: Vec2 = Vec2::new(31.7, 11.3);
+// each emitter gets its own instance buffer, this keeps a bad config from eating the gpu
+const MAX_PARTICLES: usize = 10_000;
+
+// one particle as the shader sees it
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug, Default)]
+pub struct ParticleInstance {
+    pub position: Vec3,
+    pub size: f32,
+    // where in the lut the color comes from
+    pub color: f32,
+    // around the up axis, in radians
+    pub rotation: f32,
+    // around a flat axis before the rotation, a quarter turn lies flat
+    pub tilt: f32,
+}
+
+impl ParticleInstance {
+    pub fn desc() -> BufferLayout {
+        BufferLayout {
+            step_mode: BufferLayoutStepMode::Instance,
+            entries: &[
+                BufferLayoutEntry {
+                    location: 5,
+                    data_type: BufferLayoutEntryDataType::Float32x3,
+                },
+                BufferLayoutEntry {
+                    location: 6,
+                    data_type: BufferLayoutEntryDataType::Float32,
+                },
+                BufferLayoutEntry {
+                    location: 7,
+                    data_type: BufferLayoutEntryDataType::Float32,
+                },
+                BufferLayoutEntry {
+                    location: 8,
+                    data_type: BufferLayoutEntryDataType::Float32,
+                },
+                BufferLayoutEntry {
+                    location: 9,
+                    data_type: BufferLayoutEntryDataType::Float32,
+                },
+            ],
+        }
+    }
+}
+
+// where particles spawn around the emitter's anchor
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EmitterShape {
+    Point,
+    // flat on the ground
+    Disc { radius: f32 },
+    // centered on the anchor, the height comes on top of the emitter's own
+    Box { size: [f32; 3] },
+    // the tips of the loaded plants' branches, the anchor and the terrain don't matter
+    PlantTips,
+}
+
+impl EmitterShape {
+    // none when there's nowhere to spawn
+    fn sample(
+        &self,
+        rng: &mut StdRng,
+        anchor: Vec2,
+        terrain: &Terrain,
+        sources: &[Vec3],
+    ) -> Option<Vec3> {
+        let offset = match self {
+            EmitterShape::Point => Vec3::ZERO,
+            EmitterShape::Disc { radius } => {
+                let angle = rng.gen_range(0.0..TAU);
+                let dist = radius * rng.gen::<f32>().sqrt();
+                let offset = Vec2::from_angle(angle) * dist;
+                Vec3::new(offset.x, 0.0, offset.y)
+            }
+            EmitterShape::Box { size } => {
+                (Vec3::new(rng.gen(), rng.gen(), rng.gen()) - 0.5) * Vec3::from(*size)
+            }
+            EmitterShape::PlantTips => {
+                return (!sources.is_empty()).then(|| sources[rng.gen_range(0..sources.len())]);
+            }
+        };
+        let pos = anchor + Vec2::new(offset.x, offset.z);
+        Some(Vec3::new(pos.x, terrain.height(pos) + offset.y, pos.y))
+    }
+}
+
+// pairs are the value at birth and at death, or a min and a max to pick from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EmitterConfig {
+    pub shape: EmitterShape,
+    // above the terrain
+    pub height: [f32; 2],
+    // per second
+    pub rate: f32,
+    pub max_particles: usize,
+    // in seconds
+    pub lifetime: [f32; 2],
+    pub velocity: [f32; 3],
+    // every axis of the velocity gets up to this much added or taken away
+    pub velocity_variation: [f32; 3],
+    pub acceleration: [f32; 3],
+    pub size: [f32; 2],
+    // lut coordinates, so the color follows the palette
+    pub color: [f32; 2],
+    // radians per second
+    pub spin: f32,
+    // radians per second around a flat axis, the wind speeds it up
+    pub tumble: f32,
+    // how hard the wind pushes, 0 ignores it
+    pub wind: f32,
+    // how much of the velocity is lost per second
+    pub drag: f32,
+    // seconds a particle stays put after spawning, like a leaf before it comes loose
+    pub detach: [f32; 2],
+    // whether particles stop on the ground instead of falling through it
+    pub settle: bool,
+    // seconds a settled particle lies on the ground before it's gone
+    pub rest: [f32; 2],
+    // seconds over which particles shrink away at the end of their life
+    pub fade: f32,
+    // particles this far from the anchor die early, so the emitter can follow the camera
+    pub max_distance: Option<f32>,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        Self {
+            shape: EmitterShape::Point,
+            height: [0.0; 2],
+            rate: 10.0,
+            max_particles: 100,
+            lifetime: [1.0; 2],
+            velocity: [0.0; 3],
+            velocity_variation: [0.0; 3],
+            acceleration: [0.0; 3],
+            size: [0.01; 2],
+            color: [0.0, 1.0],
+            spin: 0.0,
+            tumble: 0.0,
+            wind: 0.0,
+            drag: 0.0,
+            detach: [0.0; 2],
+            settle: false,
+            rest: [0.0; 2],
+            fade: 0.0,
+            max_distance: None,
+        }
+    }
+}
+
+impl EmitterConfig {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let config: Self = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.rate.is_finite() || self.rate < 0.0 {
+            return Err(format!("rate has to be zero or more, got {}", self.rate));
+        }
+        let [min, max] = self.lifetime;
+        if !min.is_finite() || !max.is_finite() || min < 0.0 || min > max {
+            return Err(format!(
+                "lifetime has to be a min and a max of zero or more, got [{min}, {max}]"
+            ));
+        }
+        if self.max_particles == 0 || self.max_particles > MAX_PARTICLES {
+            return Err(format!(
+                "max_particles has to be between 1 and {MAX_PARTICLES}, got {}",
+                self.max_particles
+            ));
+        }
+        Ok(())
+    }
+}
+
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    rotation: f32,
+    tilt: f32,
+    age: f32,
+    lifetime: f32,
+    // seconds left before it starts moving
+    detach: f32,
+    settled: bool,
+}
+
+pub struct Emitter {
+    pub name: String,
+    config: EmitterConfig,
+    particles: Vec<Particle>,
+    // rate * dt rarely comes out whole, the rest carries over to the next frame
+    to_spawn: f32,
+    object: AgeObject<ParticleInstance>,
+}
+
+impl Emitter {
+    pub fn new(renderer: &mut dyn Renderer, name: String, config: EmitterConfig) -> Self {
+        Self {
+            name,
+            config,
+            particles: Vec::new(),
+            to_spawn: 0.0,
+            object: AgeObject::quad(renderer, 0.0, Vec::new()),
+        }
+    }
+
+    fn update(
+        &mut self,
+        dt: f32,
+        anchor: Vec2,
+        terrain: &Terrain,
+        sources: &[Vec3],
+        wind: &dyn Fn(Vec2) -> f32,
+        rng: &mut StdRng,
+    ) {
+        let config = &self.config;
+
+        let acceleration = Vec3::from(config.acceleration);
+        for particle in self.particles.iter_mut() {
+            particle.age += dt;
+            if particle.detach > 0.0 {
+                particle.detach -= dt;
+                continue;
+            }
+            if particle.settled {
+                continue;
+            }
+
+            let pos = Vec2::new(particle.position.x, particle.position.z);
+            let push = wind(pos) * config.wind;
+            particle.velocity += (acceleration + Vec3::Z * push) * dt;
+            particle.velocity *= (1.0 - config.drag * dt).max(0.0);
+            particle.position += particle.velocity * dt;
+            particle.rotation += config.spin * dt;
+            particle.tilt += config.tumble * (1.0 + push.abs()) * dt;
+
+            let pos = Vec2::new(particle.position.x, particle.position.z);
+            let ground = terrain.height(pos);
+            if config.settle && particle.position.y <= ground {
+                particle.position.y = ground;
+                particle.tilt = FRAC_PI_2;
+                particle.settled = true;
+                particle.lifetime = particle.age + range(rng, config.rest);
+            }
+        }
+
+        self.particles.retain(|particle| {
+            let pos = Vec2::new(particle.position.x, particle.position.z);
+            particle.age < particle.lifetime
+                && config
+                    .max_distance
+                    .map_or(true, |max| pos.distance(anchor) <= max)
+        });
+
+        // a full emitter or a long frame can't bank spawns for later
+        let room = config.max_particles.saturating_sub(self.particles.len());
+        self.to_spawn = (self.to_spawn + config.rate * dt).min(room as f32);
+        while self.to_spawn >= 1.0 {
+            self.to_spawn -= 1.0;
+            let Some(position) = config.shape.sample(rng, anchor, terrain, sources) else {
+                continue;
+            };
+
+            let variation = (Vec3::new(rng.gen(), rng.gen(), rng.gen()) * 2.0 - 1.0)
+                * Vec3::from(config.velocity_variation);
+            self.particles.push(Particle {
+                position: position + Vec3::Y * range(rng, config.height),
+                velocity: Vec3::from(config.velocity) + variation,
+                rotation: rng.gen_range(0.0..TAU),
+                tilt: 0.0,
+                age: 0.0,
+                lifetime: range(rng, config.lifetime).max(0.0),
+                detach: range(rng, config.detach),
+                settled: false,
+            });
+        }
+
+        self.object.instances = self
+            .particles
+            .iter()
+            .map(|particle| {
+                let t = (particle.age / particle.lifetime.max(f32::EPSILON)).min(1.0);
+                let fade = if config.fade > 0.0 {
+                    ((particle.lifetime - particle.age) / config.fade).clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+                ParticleInstance {
+                    position: particle.position,
+                    size: lerp(config.size, t) * fade,
+                    color: lerp(config.color, t),
+                    rotation: particle.rotation,
+                    tilt: particle.tilt,
+                }
+            })
+            .collect();
+    }
+}
+
+// every emitter follows the spot the camera is looking at
+pub struct Particles {
+    emitters: Vec<Emitter>,
+    // world space points for emitters that spawn from the plants
+    sources: Vec<Vec3>,
+}
+
+impl Particles {
+    pub fn new(renderer: &mut dyn Renderer) -> Self {
+        let dust = EmitterConfig::from_json(DUST_JSON).unwrap();
+        let leaves = EmitterConfig::from_json(LEAVES_JSON).unwrap();
+        Self {
+            emitters: vec![
+                Emitter::new(renderer, DUST_EMITTER.to_string(), dust),
+                Emitter::new(renderer, LEAVES_EMITTER.to_string(), leaves),
+            ],
+            sources: Vec::new(),
+        }
+    }
+
+    // replaces any emitter with the same name
+    pub fn add(&mut self, renderer: &mut dyn Renderer, name: String, config: EmitterConfig) {
+        self.remove(&name);
+        self.emitters.push(Emitter::new(renderer, name, config));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.emitters.retain(|e| e.name != name);
+    }
+
+    pub fn set_sources(&mut self, sources: Vec<Vec3>) {
+        self.sources = sources;
+    }
+
+    pub fn update(
+        &mut self,
+        renderer: &mut dyn Renderer,
+        dt: f32,
+        anchor: Vec2,
+        terrain: &Terrain,
+        wind: impl Fn(Vec2) -> f32,
+        rng: &mut StdRng,
+    ) {
+        for emitter in self.emitters.iter_mut() {
+            emitter.update(dt, anchor, terrain, &self.sources, &wind, rng);
+            emitter.object.update(renderer);
+        }
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = &AgeObject<ParticleInstance>> {
+        self.emitters.iter().map(|e| &e.object)
+    }
+}
+
+// matches calculate_wind in the shader, pushing along z, noise is the ground noise
+pub fn wind(data: &RenderDataData, noise: &dyn Noise, pos: Vec2) -> f32 {
+    let time = data.time * data.wind_speed;
+    // offset so gusts don't follow the biome borders, which use the same field
+    let uv = pos * data.wind_noise_scale + time * 0.01 + WIND_NOISE_OFFSET;
+    let noise = noise.sample(uv.x, uv.y);
+
+    let pos = (pos.x + pos.y) + noise * data.wind_noise_strength;
+    (pos * data.wind_scale + time).sin() * data.wind_strength
+}
+
+fn range(rng: &mut StdRng, [min, max]: [f32; 2]) -> f32 {
+    min + (max - min) * rng.gen::<f32>()
+}
+
+fn lerp([from, to]: [f32; 2], t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmitterConfig, DUST_JSON, LEAVES_JSON, MAX_PARTICLES};
+
+    #[test]
+    fn bundled_emitters_are_valid() {
+        assert!(EmitterConfig::from_json(DUST_JSON).is_ok());
+        assert!(EmitterConfig::from_json(LEAVES_JSON).is_ok());
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        for json in [
+            r#"{ "rate": -1.0 }"#,
+            r#"{ "lifetime": [-1.0, 2.0] }"#,
+            r#"{ "lifetime": [3.0, 2.0] }"#,
+            r#"{ "max_particles": 0 }"#,
+        ] {
+            assert!(EmitterConfig::from_json(json).is_err(), "{json}");
+        }
+        let json = format!(r#"{{ "max_particles": {} }}"#, MAX_PARTICLES + 1);
+        assert!(EmitterConfig::from_json(&json).is_err());
+    }
+}
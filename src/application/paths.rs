@@ -0,0 +1,79 @@
+use jandering_engine::types::Vec2;
+
+use crate::noise::{Cached, DomainWarp, Fbm, Noise, Perlin};
+
+use super::scatter::smoothstep;
+
+// paths run along where the noise crosses this, which makes for long winding lines
+const PATH_LEVEL: f32 = 0.5;
+const PATH_FREQUENCY: f32 = 0.04;
+// full width of the walkable part, the edges blend into the ground over PATH_EDGE,
+// both have to match the shader
+const PATH_WIDTH: f32 = 0.35;
+const PATH_EDGE: f32 = 0.1;
+// plants stand at least this far from the edge of a path
+const PLANT_MARGIN: f32 = 0.3;
+// the field is cached on a lattice this far apart
+const LATTICE_STEP: f32 = 0.125;
+
+// meandering paths through the whole world, the same seed always carves the same paths
+pub struct Paths {
+    noise: Cached<DomainWarp<Fbm<Perlin>, Fbm<Perlin>>>,
+}
+
+impl Paths {
+    pub fn new(seed: u64) -> Self {
+        // offset so paths don't line up with the terrain, which uses the seeds just below
+        let noise = DomainWarp {
+            source: Fbm::new(
+                Perlin {
+                    seed: seed.wrapping_add(3),
+                },
+                3,
+                PATH_FREQUENCY,
+            ),
+            warp: Fbm::new(
+                Perlin {
+                    seed: seed.wrapping_add(4),
+                },
+                2,
+                0.1,
+            ),
+            strength: 2.0,
+        };
+        Self {
+            noise: Cached::new(noise, LATTICE_STEP),
+        }
+    }
+
+    // roughly how far pos is from the middle of the closest path
+    pub fn distance(&self, pos: Vec2) -> f32 {
+        self.signed_distance(pos).abs()
+    }
+
+    // negative on one side of the middle of a path, this goes smoothly through the middle so the
+    // terrain can interpolate it between vertices and leave the edges to the fragment shader
+    pub fn signed_distance(&self, pos: Vec2) -> f32 {
+        let eps = LATTICE_STEP;
+        let gradient = Vec2::new(
+            self.sample(pos + Vec2::X * eps) - self.sample(pos - Vec2::X * eps),
+            self.sample(pos + Vec2::Y * eps) - self.sample(pos - Vec2::Y * eps),
+        ) / (2.0 * eps);
+        // the field divided by its slope keeps paths the same width on steep and flat noise
+        (self.sample(pos) - PATH_LEVEL) / gradient.length().max(1e-4)
+    }
+
+    // 1 on the path, 0 off it, matches fs_floor
+    pub fn mask(&self, pos: Vec2) -> f32 {
+        let half_width = PATH_WIDTH * 0.5;
+        1.0 - smoothstep(half_width, half_width + PATH_EDGE, self.distance(pos))
+    }
+
+    pub fn blocks_plant(&self, pos: Vec2) -> bool {
+        self.distance(pos) < PATH_WIDTH * 0.5 + PATH_EDGE + PLANT_MARGIN
+    }
+
+    fn sample(&self, pos: Vec2) -> f32 {
+        self.noise.sample(pos.x, pos.y)
+    }
+}
@@ -0,0 +1,218 @@
+use std::f32::consts::{PI, TAU};
+
+use jandering_engine::types::Vec2;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::noise::Noise;
+
+// side of a square chunk in world units, placement only ever looks at one chunk at a time
+pub const CHUNK_SIZE: f32 = 6.0;
+
+// anything denser would build thousands of plants per chunk
+const MIN_SPACING: f32 = 0.25;
+// plants are only built when their chunk loads, so this keeps loading a chunk from stalling a frame
+const MAX_PLANTS_PER_CHUNK: usize = 150;
+
+// a plant is the chunk it was placed by and its index in that chunk
+pub type PlantKey = ((i32, i32), usize);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlacementStrategy {
+    Grid,
+    // jitter goes from 0 to 1, as a fraction of the spacing
+    JitteredGrid { jitter: f32 },
+    PoissonDisk { min_distance: f32 },
+    // a jittered grid at half the spacing, keeping only spots where the noise is above threshold
+    NoiseThreshold { threshold: f32, scale: f32 },
+    Clustered { clusters: u32, radius: f32 },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Placement {
+    pub strategy: PlacementStrategy,
+    #[serde(default = "default_spacing")]
+    pub spacing: f32,
+}
+
+fn default_spacing() -> f32 {
+    3.0
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self {
+            strategy: PlacementStrategy::JitteredGrid { jitter: 0.35 },
+            spacing: default_spacing(),
+        }
+    }
+}
+
+impl Placement {
+    // the same chunk and seed always give the same positions, in world space
+    pub fn place(&self, chunk: (i32, i32), seed: u64, noise: &dyn Noise) -> Vec<Vec2> {
+        let mut rng = StdRng::seed_from_u64(cell_seed(seed, chunk));
+        let origin = Vec2::new(chunk.0 as f32, chunk.1 as f32) * CHUNK_SIZE;
+        let spacing = self.spacing.max(MIN_SPACING);
+
+        let mut positions = match self.strategy {
+            PlacementStrategy::Grid => grid(origin, spacing),
+            PlacementStrategy::JitteredGrid { jitter } => {
+                jittered_grid(origin, spacing, jitter, &mut rng)
+            }
+            PlacementStrategy::PoissonDisk { min_distance } => {
+                poisson_disk(origin, min_distance.max(MIN_SPACING), seed)
+            }
+            PlacementStrategy::NoiseThreshold { threshold, scale } => {
+                jittered_grid(origin, spacing * 0.5, 0.8, &mut rng)
+                    .into_iter()
+                    .filter(|pos| noise.sample(pos.x * scale, pos.y * scale) > threshold)
+                    .collect()
+            }
+            PlacementStrategy::Clustered { clusters, radius } => {
+                clustered(origin, spacing, clusters, radius, &mut rng)
+            }
+        };
+
+        // a random pick rather than the first ones, which would fill the chunk from one side
+        if positions.len() > MAX_PLANTS_PER_CHUNK {
+            positions.partial_shuffle(&mut rng, MAX_PLANTS_PER_CHUNK);
+            positions.truncate(MAX_PLANTS_PER_CHUNK);
+        }
+        positions
+    }
+}
+
+// every plant gets its own rng so it grows the same whenever its chunk comes back into view
+pub fn plant_seed(seed: u64, key: PlantKey) -> u64 {
+    cell_seed(cell_seed(seed, key.0), (key.1 as i32, 0))
+}
+
+// offset from the plants' seed so the grass doesn't follow the plant layout
+pub fn grass_seed(seed: u64, chunk: (i32, i32)) -> u64 {
+    cell_seed(seed.wrapping_add(1), chunk)
+}
+
+pub fn creature_seed(seed: u64, chunk: (i32, i32)) -> u64 {
+    cell_seed(seed.wrapping_add(2), chunk)
+}
+
+fn cell_seed(seed: u64, pos: (i32, i32)) -> u64 {
+    let mut h = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    h ^= (pos.0 as u32 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    h = h.rotate_left(31).wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    h ^= (pos.1 as u32 as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+    h ^ (h >> 33)
+}
+
+// aligned to the world rather than the chunk, so chunks tile into one lattice
+fn grid(origin: Vec2, spacing: f32) -> Vec<Vec2> {
+    let start = (origin / spacing).ceil() * spacing;
+    let mut positions = Vec::new();
+    let mut x = start.x;
+    while x < origin.x + CHUNK_SIZE {
+        let mut z = start.y;
+        while z < origin.y + CHUNK_SIZE {
+            positions.push(Vec2::new(x, z));
+            z += spacing;
+        }
+        x += spacing;
+    }
+    positions
+}
+
+fn jittered_grid(origin: Vec2, spacing: f32, jitter: f32, rng: &mut StdRng) -> Vec<Vec2> {
+    let jitter = jitter.clamp(0.0, 1.0) * spacing * 0.5;
+    grid(origin, spacing)
+        .into_iter()
+        .map(|pos| {
+            if jitter > 0.0 {
+                pos + Vec2::new(
+                    rng.gen_range(-jitter..jitter),
+                    rng.gen_range(-jitter..jitter),
+                )
+            } else {
+                pos
+            }
+        })
+        .collect()
+}
+
+// every cell of a world aligned lattice throws one dart with a random rank, a dart is kept when it
+// outranks every other dart closer than min_distance, which only depends on the cells around it so
+// neighbouring chunks agree on who wins across the border
+fn poisson_disk(origin: Vec2, min_distance: f32, seed: u64) -> Vec<Vec2> {
+    // offset so the darts don't line up with the chunk seeds
+    let seed = seed.wrapping_add(3);
+    let cell = min_distance;
+    let dart = |x: i32, z: i32| {
+        let h = cell_seed(seed, (x, z));
+        let offset = Vec2::new((h & 0xFF_FFFF) as f32, ((h >> 24) & 0xFF_FFFF) as f32);
+        let pos = (Vec2::new(x as f32, z as f32) + offset / (1 << 24) as f32) * cell;
+        (pos, h >> 48)
+    };
+
+    let start = (origin / cell).floor();
+    let end = ((origin + Vec2::splat(CHUNK_SIZE)) / cell).ceil();
+    let (start_x, start_z, end_x, end_z) =
+        (start.x as i32, start.y as i32, end.x as i32, end.y as i32);
+
+    let mut positions = Vec::new();
+    for x in start_x..end_x {
+        for z in start_z..end_z {
+            let (pos, rank) = dart(x, z);
+            let local = pos - origin;
+            if local.min_element() < 0.0 || local.max_element() >= CHUNK_SIZE {
+                continue;
+            }
+
+            // one cell either way covers min_distance, ties go to the later cell
+            let outranked = (-1..=1).any(|i| {
+                (-1..=1).any(|j| {
+                    let (other, other_rank) = dart(x + i, z + j);
+                    (i, j) != (0, 0)
+                        && other.distance(pos) < min_distance
+                        && (other_rank, (i, j)) > (rank, (0, 0))
+                })
+            });
+            if !outranked {
+                positions.push(pos);
+            }
+        }
+    }
+    positions
+}
+
+// cluster centers stay inside the chunk, their plants may spill over into the neighbours
+fn clustered(
+    origin: Vec2,
+    spacing: f32,
+    clusters: u32,
+    radius: f32,
+    rng: &mut StdRng,
+) -> Vec<Vec2> {
+    let radius = radius.max(0.0);
+    // anything past the cap would be thrown away anyway
+    let clusters = (clusters as usize).min(MAX_PLANTS_PER_CHUNK);
+    let per_cluster = (((PI * radius * radius) / (spacing * spacing)).round() as usize)
+        .min(MAX_PLANTS_PER_CHUNK / clusters.max(1));
+
+    let mut positions = Vec::new();
+    for _ in 0..clusters {
+        let center = origin
+            + Vec2::new(
+                rng.gen_range(0.0..CHUNK_SIZE),
+                rng.gen_range(0.0..CHUNK_SIZE),
+            );
+        positions.push(center);
+        for _ in 0..per_cluster {
+            let angle = rng.gen_range(0.0..TAU);
+            let dist = radius * rng.gen::<f32>().sqrt();
+            positions.push(center + Vec2::from_angle(angle) * dist);
+        }
+    }
+    positions
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use jandering_engine::types::Vec3;
+
+use crate::color_obj::AgeVertex;
+
+use super::{logic::PlantMesh, placement::PlantKey};
+
+// how much mesh data evicted plants can keep around
+pub const PLANT_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+struct CachedPlant {
+    mesh: PlantMesh,
+    last_used: u64,
+}
+
+impl CachedPlant {
+    fn size(&self) -> usize {
+        self.mesh.vertices.len() * std::mem::size_of::<AgeVertex>()
+            + self.mesh.indices.len() * std::mem::size_of::<u32>()
+            + self.mesh.tips.len() * std::mem::size_of::<Vec3>()
+    }
+}
+
+// meshes of plants that went out of view, so coming back to a cell doesn't rebuild its plant
+// the least recently evicted ones get dropped first once the cache is over its budget
+pub struct PlantCache {
+    entries: HashMap<PlantKey, CachedPlant>,
+    budget: usize,
+    size: usize,
+    clock: u64,
+}
+
+impl PlantCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget,
+            size: 0,
+            clock: 0,
+        }
+    }
+
+    pub fn insert(&mut self, key: PlantKey, mesh: PlantMesh) {
+        self.clock += 1;
+        let plant = CachedPlant {
+            mesh,
+            last_used: self.clock,
+        };
+        if plant.size() > self.budget {
+            return;
+        }
+
+        self.size += plant.size();
+        if let Some(old) = self.entries.insert(key, plant) {
+            self.size -= old.size();
+        }
+
+        while self.size > self.budget {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, plant)| plant.last_used)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            let plant = self.entries.remove(&oldest).unwrap();
+            self.size -= plant.size();
+        }
+    }
+
+    // the plant leaves the cache, it's alive again
+    pub fn take(&mut self, key: &PlantKey) -> Option<PlantMesh> {
+        let plant = self.entries.remove(key)?;
+        self.size -= plant.size();
+        Some(plant.mesh)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.size = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PlantCache, PLANT_CACHE_BYTES};
+    use crate::{
+        application::{logic::PlantMesh, placement::PlantKey},
+        color_obj::AgeVertex,
+    };
+
+    const VERTEX: usize = std::mem::size_of::<AgeVertex>();
+
+    fn mesh(bytes: usize) -> PlantMesh {
+        PlantMesh {
+            vertices: vec![AgeVertex::default(); bytes / VERTEX],
+            indices: Vec::new(),
+            tips: Vec::new(),
+        }
+    }
+
+    fn key(i: i32) -> PlantKey {
+        ((i, 0), 0)
+    }
+
+    #[test]
+    fn stays_within_the_budget() {
+        let plant = 1024 * 1024;
+        let mut cache = PlantCache::new(PLANT_CACHE_BYTES);
+        for i in 0..80 {
+            cache.insert(key(i), mesh(plant));
+            assert!(cache.size <= PLANT_CACHE_BYTES);
+        }
+        assert_eq!(cache.entries.len(), PLANT_CACHE_BYTES / plant);
+        assert_eq!(cache.size, PLANT_CACHE_BYTES);
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_first() {
+        let mut cache = PlantCache::new(3 * VERTEX);
+        for i in 0..3 {
+            cache.insert(key(i), mesh(VERTEX));
+        }
+        cache.insert(key(3), mesh(VERTEX));
+        assert!(cache.take(&key(0)).is_none());
+        for i in 1..4 {
+            assert!(cache.take(&key(i)).is_some(), "{i}");
+        }
+        assert_eq!(cache.size, 0);
+    }
+
+    #[test]
+    fn a_hit_makes_the_plant_the_newest() {
+        let mut cache = PlantCache::new(3 * VERTEX);
+        for i in 0..3 {
+            cache.insert(key(i), mesh(VERTEX));
+        }
+        // the plant comes back into view and goes out again
+        let plant = cache.take(&key(0)).unwrap();
+        assert_eq!(cache.size, 2 * VERTEX);
+        cache.insert(key(0), plant);
+
+        cache.insert(key(3), mesh(VERTEX));
+        assert!(cache.take(&key(1)).is_none());
+        assert!(cache.take(&key(0)).is_some());
+    }
+
+    #[test]
+    fn replacing_a_plant_keeps_the_size_right() {
+        let mut cache = PlantCache::new(10 * VERTEX);
+        cache.insert(key(0), mesh(4 * VERTEX));
+        cache.insert(key(0), mesh(2 * VERTEX));
+        assert_eq!(cache.size, 2 * VERTEX);
+        assert_eq!(cache.take(&key(0)).unwrap().vertices.len(), 2);
+    }
+
+    #[test]
+    fn plants_over_the_budget_are_not_cached() {
+        let mut cache = PlantCache::new(3 * VERTEX);
+        cache.insert(key(0), mesh(VERTEX));
+        cache.insert(key(1), mesh(4 * VERTEX));
+        assert!(cache.take(&key(1)).is_none());
+        // and they don't push anything else out
+        assert!(cache.take(&key(0)).is_some());
+    }
+}
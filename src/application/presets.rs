@@ -0,0 +1,179 @@
+use jandering_engine::{types::Vec3, utils::load_text};
+use serde::{Deserialize, Serialize};
+
+use crate::l_system::config::LConfig;
+
+use super::ui::ColorStops;
+
+pub const USER_PRESET_KEY: &str = "user_preset";
+pub const USER_PRESET_NAME: &str = "custom";
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PaletteStopJSON {
+    pub position: u32,
+    pub color: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct PresetMeta {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iterations: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<Vec<PaletteStopJSON>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+}
+
+// a single self contained preset, what gets imported, exported and saved
+#[derive(Deserialize, Serialize)]
+pub struct PresetFile {
+    #[serde(flatten)]
+    pub meta: PresetMeta,
+    pub config: serde_json::Value,
+}
+
+// older manifests are just a list of names
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ManifestEntryJSON {
+    Name(String),
+    Meta(PresetMeta),
+}
+
+impl From<ManifestEntryJSON> for PresetMeta {
+    fn from(val: ManifestEntryJSON) -> Self {
+        match val {
+            ManifestEntryJSON::Name(name) => PresetMeta {
+                name,
+                ..Default::default()
+            },
+            ManifestEntryJSON::Meta(meta) => meta,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub meta: PresetMeta,
+    pub text: String,
+}
+
+impl PresetMeta {
+    pub fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn palette(&self) -> Option<ColorStops> {
+        self.palette
+            .as_ref()
+            .and_then(|stops| parse_palette(stops).ok())
+    }
+}
+
+impl PresetFile {
+    pub fn new(name: &str, config: &LConfig, color_stops: &[(u32, Vec3)]) -> Self {
+        let palette = color_stops
+            .iter()
+            .map(|(position, color)| PaletteStopJSON {
+                position: *position,
+                color: color_to_hex(*color),
+            })
+            .collect();
+
+        Self {
+            meta: PresetMeta {
+                name: name.to_string(),
+                iterations: Some(config.rules.iterations),
+                palette: Some(palette),
+                ..Default::default()
+            },
+            config: serde_json::from_str(&config.to_json()).unwrap_or_default(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Preset, String> {
+        let PresetFile { meta, config } =
+            serde_json::from_str::<PresetFile>(json).map_err(|err| err.to_string())?;
+        if let Some(palette) = &meta.palette {
+            parse_palette(palette)?;
+        }
+        let text = serde_json::to_string_pretty(&config).map_err(|err| err.to_string())?;
+        LConfig::from_json(text.clone())?;
+        Ok(Preset { meta, text })
+    }
+}
+
+pub fn color_to_hex(color: Vec3) -> String {
+    let color = (color.clamp(Vec3::ZERO, Vec3::ONE) * 255.0).round();
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        color.x as u8, color.y as u8, color.z as u8
+    )
+}
+
+pub fn parse_palette(stops: &[PaletteStopJSON]) -> Result<ColorStops, String> {
+    stops
+        .iter()
+        .map(|PaletteStopJSON { position, color }| {
+            hex_color::HexColor::parse(color)
+                .map(|e| {
+                    let color =
+                        Vec3::new(e.r as f32 / 255.0, e.g as f32 / 255.0, e.b as f32 / 255.0);
+                    (*position, color)
+                })
+                .map_err(|err| format!("{color}: {err}"))
+        })
+        .collect()
+}
+
+// loads the manifest and every preset in it, presets that fail to load or parse are skipped
+pub async fn load_presets() -> Vec<Preset> {
+    let manifest = match load_text(jandering_engine::utils::FilePath::FileName("presets.json"))
+        .await
+        .map_err(|err| format!("{err:?}"))
+        .and_then(|text| {
+            serde_json::from_str::<Vec<ManifestEntryJSON>>(&text).map_err(|err| err.to_string())
+        }) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            log::warn!("couldn't load presets.json: {err}");
+            return Vec::new();
+        }
+    };
+
+    let presets = manifest.into_iter().map(|entry| async move {
+        let meta = PresetMeta::from(entry);
+        let path = format!("systems/{}.json", meta.name);
+        let text = match load_text(jandering_engine::utils::FilePath::FileName(&path)).await {
+            Ok(text) => text,
+            Err(err) => {
+                log::warn!("couldn't load preset {}: {err:?}", meta.name);
+                return None;
+            }
+        };
+        if let Err(err) = LConfig::from_json(text.clone()) {
+            log::warn!("preset {} is invalid: {err}", meta.name);
+            return None;
+        }
+        Some(Preset { meta, text })
+    });
+
+    futures::future::join_all(presets)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
@@ -0,0 +1,42 @@
+use jandering_engine::types::Vec2;
+use rand::{rngs::StdRng, Rng};
+
+// nothing gets scattered within radius of center, like around plant trunks
+#[derive(Debug, Clone, Copy)]
+pub struct Exclusion {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Exclusion {
+    pub fn contains(&self, pos: Vec2) -> bool {
+        self.center.distance_squared(pos) < self.radius * self.radius
+    }
+}
+
+// rejection sampling, each candidate is kept with a chance of density(pos) which goes from 0 to 1
+// gives up after `attempts`, so sparse areas stay sparse instead of spinning forever
+pub fn rejection_sample(
+    rng: &mut StdRng,
+    attempts: u32,
+    mut candidate: impl FnMut(&mut StdRng) -> Vec2,
+    density: impl Fn(Vec2) -> f32,
+    exclusions: &[Exclusion],
+) -> Option<Vec2> {
+    for _ in 0..attempts {
+        let pos = candidate(rng);
+        if exclusions.iter().any(|e| e.contains(pos)) {
+            continue;
+        }
+        if rng.gen::<f32>() < density(pos) {
+            return Some(pos);
+        }
+    }
+    None
+}
+
+// smooth ramp from 0 at edge0 to 1 at edge1
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
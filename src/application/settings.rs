@@ -0,0 +1,198 @@
+use crate::l_system::config::LConfig;
+
+use super::ui::{ColorStops, UiBridge};
+
+// what the settings ui changed since the last update
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SettingsChanges {
+    // the grammar or the iteration count, so the plants have to be rebuilt
+    pub l_system: bool,
+    pub color_stops: bool,
+}
+
+impl SettingsChanges {
+    pub fn any(&self) -> bool {
+        self.l_system || self.color_stops
+    }
+}
+
+// the state the settings ui drives, kept apart from the renderer so it runs without one
+pub struct Settings<U: UiBridge + ?Sized = dyn UiBridge> {
+    pub ui: Box<U>,
+    pub l_config: LConfig,
+    pub color_stops: ColorStops,
+}
+
+impl<U: UiBridge + ?Sized> Settings<U> {
+    pub fn new(mut ui: Box<U>) -> Self {
+        let color_stops = ui.read_color_stops().unwrap_or_default();
+        Self {
+            ui,
+            l_config: LConfig::default(),
+            color_stops,
+        }
+    }
+
+    pub fn update(&mut self) -> SettingsChanges {
+        let config = self.update_config();
+        let iterations = self.update_iteration_count();
+        SettingsChanges {
+            l_system: config || iterations,
+            color_stops: self.update_color_stops(),
+        }
+    }
+
+    fn update_config(&mut self) -> bool {
+        let Some(config) = self.ui.read_lsystem() else {
+            return false;
+        };
+
+        match LConfig::from_json(config) {
+            Ok(mut l_config) => {
+                // the iteration count comes from the ui, not the config
+                l_config
+                    .rules
+                    .set_iterations(self.l_config.rules.iterations);
+                self.l_config = l_config;
+                self.ui.display_error("");
+                true
+            }
+            Err(error) => {
+                self.ui.display_error(&error);
+                false
+            }
+        }
+    }
+
+    fn update_iteration_count(&mut self) -> bool {
+        let Some(value) = self.ui.read_iterations() else {
+            return false;
+        };
+        if self.l_config.rules.iterations == value {
+            return false;
+        }
+
+        let previous = self.l_config.rules.iterations;
+        if !self.l_config.rules.set_iterations(value) {
+            log::warn!(
+                "this l-system only grows up to {} iterations",
+                self.l_config.rules.iterations
+            );
+        }
+        self.l_config.rules.iterations != previous
+    }
+
+    fn update_color_stops(&mut self) -> bool {
+        match self.ui.read_color_stops() {
+            Some(color_stops) => {
+                self.color_stops = color_stops;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jandering_engine::types::Vec3;
+
+    use super::{Settings, SettingsChanges};
+    use crate::{
+        application::{
+            presets::{Preset, PresetMeta},
+            ui::{MemoryUi, UiBridge},
+        },
+        l_system::generator::{generate, GeneratorKnobs, GENERATED_ITERATIONS},
+    };
+
+    const SYSTEM: &str = include_str!("../../res/systems/normal tree.json");
+
+    fn settings() -> Settings<MemoryUi> {
+        let mut settings = Settings::new(Box::<MemoryUi>::default());
+        settings.ui.set_iterations(5);
+        settings.update();
+        settings
+    }
+
+    #[test]
+    fn nothing_changes_without_input() {
+        let mut settings = settings();
+        assert_eq!(settings.update(), SettingsChanges::default());
+        assert!(!settings.update().any());
+    }
+
+    #[test]
+    fn lsystem_keeps_the_ui_iteration_count() {
+        let mut settings = settings();
+        settings.ui.set_lsystem(SYSTEM.to_string());
+        let changes = settings.update();
+        assert!(changes.l_system);
+        assert!(!changes.color_stops);
+        assert_eq!(settings.l_config.rules.iterations, 5);
+        assert!(settings.ui.error.is_empty());
+    }
+
+    #[test]
+    fn invalid_lsystem_shows_an_error() {
+        let mut settings = settings();
+        settings.ui.set_lsystem(SYSTEM.to_string());
+        settings.update();
+        let before = settings.l_config.clone();
+
+        settings.ui.set_lsystem("{ not json".to_string());
+        assert!(!settings.update().l_system);
+        assert!(!settings.ui.error.is_empty());
+        assert_eq!(settings.l_config, before);
+    }
+
+    #[test]
+    fn iteration_count() {
+        let mut settings = settings();
+        settings.ui.set_iterations(5);
+        assert!(!settings.update().l_system);
+
+        settings.ui.set_iterations(7);
+        assert!(settings.update().l_system);
+        assert_eq!(settings.l_config.rules.iterations, 7);
+    }
+
+    #[test]
+    fn generated_lsystems_cap_the_iteration_count() {
+        let mut settings = settings();
+        let config = generate(1, &GeneratorKnobs::default());
+        settings.ui.set_lsystem(config.to_json());
+        settings.update();
+
+        settings.ui.set_iterations(GENERATED_ITERATIONS + 10);
+        settings.update();
+        assert_eq!(settings.l_config.rules.iterations, GENERATED_ITERATIONS);
+    }
+
+    #[test]
+    fn color_stops() {
+        let mut settings = settings();
+        let stops = vec![(0, Vec3::ZERO), (4, Vec3::ONE)];
+        settings.ui.set_color_stops(stops.clone());
+        let changes = settings.update();
+        assert!(changes.color_stops);
+        assert!(!changes.l_system);
+        assert_eq!(settings.color_stops, stops);
+    }
+
+    #[test]
+    fn presets_switch_everything_at_once() {
+        let mut settings = settings();
+        settings.ui.add_preset(Preset {
+            meta: PresetMeta {
+                name: "tree".to_string(),
+                iterations: Some(8),
+                ..Default::default()
+            },
+            text: SYSTEM.to_string(),
+        });
+        let changes = settings.update();
+        assert!(changes.l_system);
+        assert_eq!(settings.l_config.rules.iterations, 8);
+    }
+}
@@ -7,7 +7,7 @@ use jandering_engine::{
             camera::free::{CameraController, MatrixCameraBindGroup},
             texture::TextureBindGroup,
         },
-        object::{Instance, Object, Vertex},
+        object::Instance,
         renderer::{
             create_typed_bind_group, create_typed_bind_group_at, get_typed_bind_group,
             BindGroupHandle, Renderer, SamplerHandle, ShaderHandle, TextureHandle,
@@ -22,12 +22,14 @@ use jandering_engine::{
 use crate::{
     camera_controller::IsometricCameraController,
     color_obj::{AgeObject, AgeVertex},
+    grass::GrassInstance,
     image::Image,
+    l_system::colors,
 };
 
 use super::{
-    logic::read_lut, Plants, RenderDataBindGroup, N_DUST, N_GRASS, ORTHO_FAR, ORTHO_HEIGHT,
-    ORTHO_NEAR, ORTHO_WIDTH, REFERENCE_DIAGONAL,
+    particles::ParticleInstance, ui::ColorStops, Plants, RenderDataBindGroup, ORTHO_FAR,
+    ORTHO_HEIGHT, ORTHO_NEAR, ORTHO_WIDTH, REFERENCE_DIAGONAL,
 };
 
 pub fn create_camera(renderer: &mut dyn Renderer) -> BindGroupHandle<MatrixCameraBindGroup> {
@@ -55,38 +57,19 @@ pub fn create_camera(renderer: &mut dyn Renderer) -> BindGroupHandle<MatrixCamer
     create_typed_bind_group(renderer, camera)
 }
 
-pub fn create_objects(
-    renderer: &mut dyn Renderer,
-) -> (Plants, Object<Instance>, AgeObject, AgeObject) {
-    let floor = Object::quad(
-        renderer,
-        vec![Instance::default()
-            .rotate(90.0f32.to_radians(), Vec3::X)
-            .set_size(Vec3::splat(100.0))],
-    );
-
+pub fn create_objects(renderer: &mut dyn Renderer) -> (Plants, AgeObject<GrassInstance>) {
     let mut plants = HashMap::new();
     plants.reserve(50);
 
-    let dust_instances = (0..N_DUST)
-        .map(|_| Instance::default().translate(Vec3::splat(-1000.0)))
-        .collect();
-    let dust = AgeObject::quad(renderer, 0.3, dust_instances);
-
-    let grass_instances = (0..N_GRASS)
-        .map(|_| {
-            Instance::default()
-                .set_size(Vec3::new(0.008, 0.1, 1.0))
-                .set_position(Vec3::new(1000.0, 0.0, 0.0))
-        })
-        .collect::<Vec<_>>();
-    let grass = AgeObject::quad(renderer, 1.0, grass_instances);
+    // filled in as chunks get loaded
+    let grass = AgeObject::blades(renderer, Vec::new());
 
-    (plants, floor, dust, grass)
+    (plants, grass)
 }
 
 pub async fn create_textures(
     renderer: &mut dyn Renderer,
+    palettes: &[ColorStops],
 ) -> (
     TextureHandle,
     Image,
@@ -96,7 +79,7 @@ pub async fn create_textures(
     BindGroupHandle<TextureBindGroup>,
 ) {
     let (lut_texture, lut_texture_linear, lut_sampler) =
-        create_lut_textures(renderer, None, None, None);
+        create_lut_textures(renderer, palettes, None, None, None);
     let depth_texture = renderer.create_texture(TextureDescriptor {
         size: renderer.size(),
         format: TextureFormat::Depth32F,
@@ -131,7 +114,13 @@ pub async fn create_textures(
 
 pub async fn create_shaders(
     renderer: &mut dyn Renderer,
-) -> (ShaderHandle, ShaderHandle, ShaderHandle, ShaderHandle) {
+) -> (
+    ShaderHandle,
+    ShaderHandle,
+    ShaderHandle,
+    ShaderHandle,
+    ShaderHandle,
+) {
     let descriptor = ShaderDescriptor::default()
         .with_source(jandering_engine::core::shader::ShaderSource::Code(
             load_text(jandering_engine::utils::FilePath::FileName(
@@ -154,19 +143,40 @@ pub async fn create_shaders(
     let floor_shader: ShaderHandle = renderer.create_shader(
         descriptor
             .clone()
-            .with_descriptors(vec![Vertex::desc(), Instance::desc()])
+            .with_vs_entry("vs_floor")
             .with_fs_entry("fs_floor"),
     );
-    let grass_shader: ShaderHandle =
-        renderer.create_shader(descriptor.clone().with_fs_entry("fs_grass"));
-    let dust_shader: ShaderHandle =
-        renderer.create_shader(descriptor.clone().with_fs_entry("fs_dust"));
+    let grass_shader: ShaderHandle = renderer.create_shader(
+        descriptor
+            .clone()
+            .with_descriptors(vec![AgeVertex::desc(), GrassInstance::desc()])
+            .with_vs_entry("vs_grass")
+            .with_fs_entry("fs_grass"),
+    );
+    let particle_shader: ShaderHandle = renderer.create_shader(
+        descriptor
+            .clone()
+            .with_descriptors(vec![AgeVertex::desc(), ParticleInstance::desc()])
+            .with_vs_entry("vs_particle")
+            .with_fs_entry("fs_particle"),
+    );
+
+    let creature_shader: ShaderHandle =
+        renderer.create_shader(descriptor.clone().with_fs_entry("fs_creature"));
 
-    (shader, floor_shader, grass_shader, dust_shader)
+    (
+        shader,
+        floor_shader,
+        grass_shader,
+        particle_shader,
+        creature_shader,
+    )
 }
 
+// every palette gets its own row of the lut
 pub fn create_lut_textures(
     renderer: &mut dyn Renderer,
+    palettes: &[ColorStops],
     lut_handle: Option<BindGroupHandle<TextureBindGroup>>,
     lut_handle_linear: Option<BindGroupHandle<TextureBindGroup>>,
     mut lut_sampler: Option<SamplerHandle>,
@@ -182,24 +192,23 @@ pub fn create_lut_textures(
         }));
     }
 
-    let data = read_lut(false)
-        .unwrap_or_default()
+    let max_len = renderer.max_texture_size().x as usize;
+    let width = palettes
         .iter()
-        .take(renderer.max_texture_size().x as usize)
-        .flat_map(|e| {
-            [
-                (e.x * 255.0) as u8,
-                (e.y * 255.0) as u8,
-                (e.z * 255.0) as u8,
-                255,
-            ]
-        })
+        .map(|e| colors::parse_colors(e).len())
+        .max()
+        .unwrap_or(0)
+        .min(max_len);
+    let rows = palettes
+        .iter()
+        .map(|e| colors::parse_colors_with_resolution(e, width))
         .collect::<Vec<_>>();
+    let data = lut_texture_data(&rows, width);
     let mut desc = TextureDescriptor {
         data: if data.is_empty() { None } else { Some(&data) },
         size: UVec2 {
-            x: (data.len() as u32 / 4).max(1),
-            y: 1,
+            x: width.max(1) as u32,
+            y: palettes.len().max(1) as u32,
         },
         format: TextureFormat::Rgba8U,
         ..Default::default()
@@ -220,22 +229,20 @@ pub fn create_lut_textures(
         create_typed_bind_group(renderer, texture)
     };
 
-    let data = read_lut(true)
-        .unwrap_or_default()
+    let width = palettes
         .iter()
-        .take(renderer.max_texture_size().x as usize)
-        .flat_map(|e| {
-            [
-                (e.x * 255.0) as u8,
-                (e.y * 255.0) as u8,
-                (e.z * 255.0) as u8,
-                255,
-            ]
-        })
+        .map(|e| colors::parse_colors_linear(e).len())
+        .max()
+        .unwrap_or(0)
+        .min(max_len);
+    let rows = palettes
+        .iter()
+        .map(|e| colors::parse_colors_linear_with_resolution(e, width))
         .collect::<Vec<_>>();
+    let data = lut_texture_data(&rows, width);
 
     desc.data = if data.is_empty() { None } else { Some(&data) };
-    desc.size.x = (data.len() as u32 / 4).max(1);
+    desc.size.x = width.max(1) as u32;
 
     let lut_texture_linear = if let Some(handle) = lut_handle_linear {
         let texture_handle = get_typed_bind_group(renderer, handle)
@@ -254,3 +261,26 @@ pub fn create_lut_textures(
 
     (lut_texture, lut_texture_linear, lut_sampler.unwrap())
 }
+
+// rows that are too short, like an empty palette, get padded with black
+fn lut_texture_data(rows: &[Vec<Vec3>], width: usize) -> Vec<u8> {
+    if width == 0 {
+        return Vec::new();
+    }
+    rows.iter()
+        .flat_map(|row| {
+            row.iter()
+                .copied()
+                .chain(std::iter::repeat(Vec3::ZERO))
+                .take(width)
+        })
+        .flat_map(|e| {
+            [
+                (e.x * 255.0) as u8,
+                (e.y * 255.0) as u8,
+                (e.z * 255.0) as u8,
+                255,
+            ]
+        })
+        .collect()
+}
@@ -0,0 +1,129 @@
+use crate::{
+    l_system::{
+        config::LConfig,
+        generator::{self, GeneratorKnobs},
+    },
+    noise::value_noise,
+};
+
+// name the main species goes by when weighting species
+pub const MAIN_SPECIES: &str = "main";
+
+// how many cells a species region spans, roughly
+const REGION_SIZE: f32 = 6.0;
+
+pub struct Species {
+    pub name: String,
+    pub config: LConfig,
+    pub weight: f32,
+    // None for species that didn't come from the generator, those never change with the seed
+    pub generator: Option<SpeciesGenerator>,
+}
+
+// how a built-in species is grown from the world seed
+#[derive(Debug, Clone, Copy)]
+pub struct SpeciesGenerator {
+    pub seed_offset: u64,
+    pub knobs: GeneratorKnobs,
+}
+
+impl SpeciesGenerator {
+    fn generate(&self, seed: u64) -> LConfig {
+        generator::generate(seed.wrapping_add(self.seed_offset), &self.knobs)
+    }
+}
+
+// species mixed in around the main one, which is the config edited through the ui
+pub struct SpeciesRegistry {
+    pub main_weight: f32,
+    pub species: Vec<Species>,
+    pub seed: u64,
+}
+
+impl SpeciesRegistry {
+    pub fn new(seed: u64) -> Self {
+        let generated = |name: &str, seed_offset: u64, knobs: GeneratorKnobs, weight: f32| {
+            let generator = SpeciesGenerator { seed_offset, knobs };
+            Species {
+                name: name.to_string(),
+                config: generator.generate(seed),
+                weight,
+                generator: Some(generator),
+            }
+        };
+
+        Self {
+            main_weight: 1.0,
+            species: vec![
+                generated(
+                    "shrub",
+                    0,
+                    GeneratorKnobs {
+                        bushiness: 0.9,
+                        symmetry: 0.4,
+                        height: 0.15,
+                    },
+                    0.6,
+                ),
+                generated(
+                    "clump",
+                    1,
+                    GeneratorKnobs {
+                        bushiness: 1.0,
+                        symmetry: 0.2,
+                        height: 0.0,
+                    },
+                    0.4,
+                ),
+            ],
+            seed,
+        }
+    }
+
+    // every species gets its own noise layer scaled by its weight and the strongest one wins,
+    // which keeps neighbouring cells mostly the same species, None means the main species
+    // `weight` scales each species by name on top of its own weight
+    pub fn pick(&self, cell: (i32, i32), weight: impl Fn(&str) -> f32) -> Option<usize> {
+        let x = cell.0 as f32 / REGION_SIZE;
+        let z = cell.1 as f32 / REGION_SIZE;
+
+        let main = value_noise(x, z, self.seed) * self.main_weight * weight(MAIN_SPECIES);
+        self.species
+            .iter()
+            .enumerate()
+            .map(|(i, species)| {
+                let seed = self.seed.wrapping_add((i as u64 + 1) * 7919);
+                (
+                    i,
+                    value_noise(x, z, seed) * species.weight * weight(&species.name),
+                )
+            })
+            .filter(|(_, strength)| *strength > main)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    // regrows the generated species for a new world seed, everything else stays as it was
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        for species in self.species.iter_mut() {
+            if let Some(generator) = species.generator {
+                species.config = generator.generate(seed);
+            }
+        }
+    }
+
+    // replaces a species with the same name
+    pub fn add(&mut self, species: Species) {
+        self.remove(&species.name);
+        self.species.push(species);
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.species.retain(|species| species.name != name);
+    }
+
+    pub fn config_mut(&mut self, i: usize) -> &mut LConfig {
+        &mut self.species[i].config
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use jandering_engine::{
+    core::{object::Instance, renderer::Renderer},
+    types::{Vec2, Vec3},
+};
+
+use crate::{
+    color_obj::{AgeObject, AgeVertex},
+    noise::{Cached, DomainWarp, Fbm, Noise, Perlin, Simplex, Worley},
+};
+
+use super::{paths::Paths, placement::CHUNK_SIZE};
+
+// the ground goes from 0 up to this
+pub const TERRAIN_HEIGHT: f32 = 0.8;
+
+// quads along one side of a chunk
+const RESOLUTION: usize = 24;
+// steps a ray takes through the height range before refining the hit
+const RAY_STEPS: usize = 64;
+const RAY_REFINE_STEPS: usize = 8;
+
+// rolling hills with a few small knolls on top
+struct TerrainNoise {
+    hills: DomainWarp<Fbm<Simplex>, Fbm<Perlin>>,
+    knolls: Worley,
+}
+
+impl TerrainNoise {
+    fn new(seed: u64) -> Self {
+        Self {
+            hills: DomainWarp {
+                source: Fbm::new(Simplex { seed }, 4, 0.08),
+                warp: Fbm::new(
+                    Perlin {
+                        seed: seed.wrapping_add(1),
+                    },
+                    2,
+                    0.05,
+                ),
+                strength: 4.0,
+            },
+            knolls: Worley {
+                seed: seed.wrapping_add(2),
+            },
+        }
+    }
+}
+
+impl Noise for TerrainNoise {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        let knoll = (1.0 - self.knolls.sample(x * 0.3, z * 0.3)).powi(3);
+        self.hills.sample(x, z) * 0.85 + knoll * 0.15
+    }
+}
+
+// ground meshes for the loaded chunks, and the heightfield they're built from
+pub struct Terrain {
+    chunks: HashMap<(i32, i32), AgeObject>,
+    // the lattice lines up with the mesh vertices
+    noise: Cached<TerrainNoise>,
+}
+
+impl Terrain {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            chunks: HashMap::new(),
+            noise: Cached::new(TerrainNoise::new(seed), CHUNK_SIZE / RESOLUTION as f32),
+        }
+    }
+
+    pub fn noise(&self) -> &dyn Noise {
+        &self.noise
+    }
+
+    pub fn height(&self, pos: Vec2) -> f32 {
+        self.noise.sample(pos.x, pos.y) * TERRAIN_HEIGHT
+    }
+
+    pub fn normal(&self, pos: Vec2) -> Vec3 {
+        let eps = CHUNK_SIZE / RESOLUTION as f32;
+        let dx = self.height(pos - Vec2::X * eps) - self.height(pos + Vec2::X * eps);
+        let dz = self.height(pos - Vec2::Y * eps) - self.height(pos + Vec2::Y * eps);
+        Vec3::new(dx, 2.0 * eps, dz).normalize()
+    }
+
+    // where a ray first hits the ground, only rays pointing down can
+    pub fn raycast(&self, origin: Vec3, dir: Vec3) -> Option<Vec3> {
+        if dir.y > -1e-6 {
+            return None;
+        }
+
+        // only the part of the ray between the highest and the lowest possible ground is marched
+        let t_start = ((TERRAIN_HEIGHT - origin.y) / dir.y).max(0.0);
+        let t_end = -origin.y / dir.y;
+        if t_end < 0.0 {
+            return None;
+        }
+
+        let above = |t: f32| {
+            let pos = origin + dir * t;
+            pos.y > self.height(Vec2::new(pos.x, pos.z))
+        };
+
+        let step = (t_end - t_start) / RAY_STEPS as f32;
+        let mut t = t_start;
+        for _ in 0..RAY_STEPS {
+            if !above(t + step) {
+                let (mut near, mut far) = (t, t + step);
+                for _ in 0..RAY_REFINE_STEPS {
+                    let mid = (near + far) * 0.5;
+                    if above(mid) {
+                        near = mid;
+                    } else {
+                        far = mid;
+                    }
+                }
+                return Some(origin + dir * far);
+            }
+            t += step;
+        }
+        Some(origin + dir * t_end)
+    }
+
+    pub fn load(&mut self, renderer: &mut dyn Renderer, chunk: (i32, i32), paths: &Paths) {
+        if self.chunks.contains_key(&chunk) {
+            return;
+        }
+        let (vertices, indices) = self.chunk_mesh(chunk, paths);
+        let object = AgeObject::new(renderer, vertices, indices, vec![Instance::default()]);
+        self.chunks.insert(chunk, object);
+    }
+
+    pub fn unload(&mut self, chunk: &(i32, i32)) {
+        self.chunks.remove(chunk);
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = &AgeObject> {
+        self.chunks.values()
+    }
+
+    // in world space, so every chunk shares the identity instance
+    // the age holds the signed distance to the closest path, fs_floor turns it into the mask
+    fn chunk_mesh(&self, chunk: (i32, i32), paths: &Paths) -> (Vec<AgeVertex>, Vec<u32>) {
+        let origin = Vec2::new(chunk.0 as f32, chunk.1 as f32) * CHUNK_SIZE;
+        let step = CHUNK_SIZE / RESOLUTION as f32;
+
+        let mut vertices = Vec::with_capacity((RESOLUTION + 1) * (RESOLUTION + 1));
+        for x in 0..=RESOLUTION {
+            for z in 0..=RESOLUTION {
+                let pos = origin + Vec2::new(x as f32, z as f32) * step;
+                vertices.push(AgeVertex {
+                    position: Vec3::new(pos.x, self.height(pos), pos.y),
+                    normal: self.normal(pos),
+                    age: paths.signed_distance(pos),
+                    ..Default::default()
+                });
+            }
+        }
+
+        let row = RESOLUTION as u32 + 1;
+        let mut indices = Vec::with_capacity(RESOLUTION * RESOLUTION * 6);
+        for x in 0..RESOLUTION as u32 {
+            for z in 0..RESOLUTION as u32 {
+                let i = x * row + z;
+                indices.extend_from_slice(&[i, i + 1, i + row, i + 1, i + row + 1, i + row]);
+            }
+        }
+
+        (vertices, indices)
+    }
+}
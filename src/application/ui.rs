@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use jandering_engine::types::Vec3;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlDivElement, HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+
+use crate::storage;
+
+use super::presets::{
+    color_to_hex, load_presets, Preset, PresetFile, USER_PRESET_KEY, USER_PRESET_NAME,
+};
+
+pub type ColorStops = Vec<(u32, Vec3)>;
+
+// everything the application needs from the settings ui
+// the read_* functions only return a value when it changed since the last call
+pub trait UiBridge {
+    fn read_lsystem(&mut self) -> Option<String>;
+
+    fn read_iterations(&mut self) -> Option<u32>;
+
+    fn read_color_stops(&mut self) -> Option<ColorStops>;
+
+    fn display_error(&mut self, error: &str);
+
+    // makes the preset available and switches to it
+    fn add_preset(&mut self, preset: Preset);
+}
+
+// the settings panel in index.html
+#[derive(Default)]
+pub struct WebUi {
+    presets: HashMap<String, Preset>,
+    iterations: Option<u32>,
+    color_stops_read: bool,
+}
+
+impl WebUi {
+    pub async fn new() -> Self {
+        Self {
+            presets: setups_js_inputs().await.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+impl UiBridge for WebUi {
+    fn read_lsystem(&mut self) -> Option<String> {
+        let window = web_sys::window()?;
+        let document = window.document()?;
+        let presets_input = document
+            .get_element_by_id("presets")?
+            .dyn_into::<HtmlSelectElement>()
+            .ok()?;
+        let presets_edit = document
+            .get_element_by_id("edit-text-input")?
+            .dyn_into::<HtmlTextAreaElement>()
+            .ok()?;
+
+        if presets_input.has_attribute("changed") {
+            if let Some(preset) = self.presets.get(&presets_input.value()) {
+                presets_edit.set_value(&preset.text);
+                let _ = presets_edit.set_attribute("changed", "");
+
+                if let Some(iterations) = preset.meta.iterations {
+                    write_iterations(iterations);
+                }
+                if let Some(palette) = preset.meta.palette() {
+                    write_color_stops(&palette);
+                }
+            }
+            let _ = presets_input.remove_attribute("changed");
+        }
+
+        if presets_edit.has_attribute("changed") {
+            let _ = presets_edit.remove_attribute("changed");
+            Some(presets_edit.value())
+        } else {
+            None
+        }
+    }
+
+    fn read_iterations(&mut self) -> Option<u32> {
+        let value = web_sys::window()?
+            .document()?
+            .get_element_by_id("detail")?
+            .dyn_into::<HtmlInputElement>()
+            .ok()?
+            .value()
+            .parse::<u32>()
+            .ok()?;
+
+        if self.iterations != Some(value) {
+            self.iterations = Some(value);
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn read_color_stops(&mut self) -> Option<ColorStops> {
+        let gradient_creator = web_sys::window()?
+            .document()?
+            .get_element_by_id("gradient-creator")?;
+
+        if self.color_stops_read && !gradient_creator.has_attribute("changed") {
+            return None;
+        }
+        let _ = gradient_creator.remove_attribute("changed");
+        self.color_stops_read = true;
+
+        read_color_stops()
+    }
+
+    fn display_error(&mut self, error: &str) {
+        if let Some(error_box) = web_sys::window()
+            .and_then(|win| win.document())
+            .and_then(|doc| doc.get_element_by_id("error-box"))
+            .and_then(|el| el.dyn_into::<HtmlDivElement>().ok())
+        {
+            error_box.set_inner_text(error);
+        }
+    }
+
+    fn add_preset(&mut self, preset: Preset) {
+        let name = preset.meta.name.clone();
+        if !self.presets.contains_key(&name) {
+            add_preset_option(&preset);
+        }
+        self.presets.insert(name.clone(), preset);
+
+        if let Some(presets_input) = web_sys::window()
+            .and_then(|win| win.document())
+            .and_then(|doc| doc.get_element_by_id("presets"))
+            .and_then(|el| el.dyn_into::<HtmlSelectElement>().ok())
+        {
+            presets_input.set_value(&name);
+            let _ = presets_input.set_attribute("changed", "");
+        }
+    }
+}
+
+// settings kept in memory, for running natively and in tests where there's no page
+#[derive(Default)]
+pub struct MemoryUi {
+    lsystem: Option<String>,
+    iterations: Option<u32>,
+    color_stops: Option<ColorStops>,
+    pub error: String,
+}
+
+impl MemoryUi {
+    pub fn set_lsystem(&mut self, lsystem: String) {
+        self.lsystem = Some(lsystem);
+    }
+
+    pub fn set_iterations(&mut self, iterations: u32) {
+        self.iterations = Some(iterations);
+    }
+
+    pub fn set_color_stops(&mut self, color_stops: ColorStops) {
+        self.color_stops = Some(color_stops);
+    }
+}
+
+impl UiBridge for MemoryUi {
+    fn read_lsystem(&mut self) -> Option<String> {
+        self.lsystem.take()
+    }
+
+    fn read_iterations(&mut self) -> Option<u32> {
+        self.iterations.take()
+    }
+
+    fn read_color_stops(&mut self) -> Option<ColorStops> {
+        self.color_stops.take()
+    }
+
+    fn display_error(&mut self, error: &str) {
+        self.error = error.to_string();
+    }
+
+    fn add_preset(&mut self, preset: Preset) {
+        if let Some(iterations) = preset.meta.iterations {
+            self.set_iterations(iterations);
+        }
+        if let Some(color_stops) = preset.meta.palette() {
+            self.set_color_stops(color_stops);
+        }
+        self.set_lsystem(preset.text);
+    }
+}
+
+fn read_color_stops() -> Option<ColorStops> {
+    let elements = web_sys::window()?
+        .document()?
+        .get_elements_by_class_name("color-stop");
+    let mut colors = Vec::with_capacity(elements.length() as usize);
+    for i in 0..elements.length() {
+        let element = elements.get_with_index(i)?;
+        if element.id() == "color-stop-template" {
+            continue;
+        }
+        let color = element
+            .children()
+            .get_with_index(1)?
+            .dyn_into::<HtmlInputElement>()
+            .unwrap();
+        let age = element
+            .last_element_child()?
+            .dyn_into::<HtmlInputElement>()
+            .unwrap();
+        let age = age.value().parse::<u32>().unwrap_or(0);
+        let color = hex_color::HexColor::parse(&color.value())
+            .map(|e| Vec3::new(e.r as f32 / 255.0, e.g as f32 / 255.0, e.b as f32 / 255.0))
+            .unwrap_or(Vec3::ZERO);
+        colors.push((age, color));
+    }
+
+    Some(colors)
+}
+
+fn write_iterations(iterations: u32) -> Option<()> {
+    web_sys::window()?
+        .document()?
+        .get_element_by_id("detail")?
+        .dyn_into::<HtmlInputElement>()
+        .ok()?
+        .set_value(&iterations.to_string());
+    Some(())
+}
+
+// replaces the stops in the gradient editor, the lut picks them up through the changed attribute
+fn write_color_stops(color_stops: &[(u32, Vec3)]) -> Option<()> {
+    let document = web_sys::window()?.document()?;
+    let gradient_creator = document.get_element_by_id("gradient-creator")?;
+    let template = document.get_element_by_id("color-stop-template")?;
+
+    gradient_creator.set_inner_html("");
+    for (age, color) in color_stops {
+        let stop = template
+            .clone_node_with_deep(true)
+            .ok()?
+            .dyn_into::<web_sys::Element>()
+            .ok()?;
+        stop.remove_attribute("id").ok()?;
+        stop.set_class_name("color-stop ui-state-default");
+
+        stop.children()
+            .get_with_index(1)?
+            .dyn_into::<HtmlInputElement>()
+            .ok()?
+            .set_value(&color_to_hex(*color));
+        stop.last_element_child()?
+            .dyn_into::<HtmlInputElement>()
+            .ok()?
+            .set_value(&age.to_string());
+
+        gradient_creator.append_child(&stop).ok()?;
+    }
+    gradient_creator.set_attribute("changed", "").ok()?;
+    Some(())
+}
+
+fn add_preset_option(preset: &Preset) -> Option<()> {
+    let document = web_sys::window()?.document()?;
+    let presets_input = document.get_element_by_id("presets")?;
+    let meta = &preset.meta;
+
+    let el = document.create_element("option").ok()?;
+    el.set_text_content(Some(meta.display_name()));
+    let _ = el.set_attribute("value", &meta.name);
+
+    let mut title = meta.description.clone().unwrap_or_default();
+    if let Some(author) = &meta.author {
+        title = format!("{title}\nby {author}");
+    }
+    if !meta.tags.is_empty() {
+        title = format!("{title}\n{}", meta.tags.join(", "));
+    }
+    let _ = el.set_attribute("title", title.trim());
+    if let Some(thumbnail) = &meta.thumbnail {
+        let _ = el.set_attribute("data-thumbnail", thumbnail);
+    }
+    presets_input.append_child(&el).ok()?;
+    Some(())
+}
+
+async fn setups_js_inputs() -> Option<HashMap<String, Preset>> {
+    let window = web_sys::window()?;
+    let document = window.document()?;
+    let presets_input = document
+        .get_element_by_id("presets")?
+        .dyn_into::<HtmlSelectElement>()
+        .ok()?;
+
+    let mut presets = load_presets().await;
+
+    // whatever the user had last time goes first
+    if let Some(user_preset) = storage::load(USER_PRESET_KEY) {
+        match PresetFile::from_json(&user_preset) {
+            Ok(mut preset) => {
+                preset.meta.name = USER_PRESET_NAME.to_string();
+                presets.insert(0, preset);
+            }
+            Err(err) => log::warn!("couldn't restore the saved preset: {err}"),
+        }
+    }
+
+    if presets.is_empty() {
+        return None;
+    }
+
+    let mut map = HashMap::new();
+
+    for preset in presets {
+        add_preset_option(&preset);
+
+        let name = preset.meta.name.clone();
+        if map.is_empty() {
+            presets_input.set_value(&name);
+            let _ = presets_input.set_attribute("changed", "");
+        }
+        map.insert(name, preset);
+    }
+    Some(map)
+}
@@ -40,25 +40,26 @@ impl AgeVertex {
     }
 }
 
+// instances default to a model matrix, anything with its own layout can be drawn the same way
 #[derive(Debug)]
-pub struct AgeObject {
+pub struct AgeObject<I = Instance> {
     pub vertices: Vec<AgeVertex>,
     //
     pub indices: Vec<u32>,
     //
-    pub instances: Vec<Instance>,
+    pub instances: Vec<I>,
     //
     pub render_data: ObjectRenderData,
 
     previous_instances_len: usize,
 }
 
-impl AgeObject {
+impl<I: bytemuck::Pod> AgeObject<I> {
     pub fn new(
         renderer: &mut dyn Renderer,
         vertices: Vec<AgeVertex>,
         indices: Vec<u32>,
-        instances: Vec<Instance>,
+        instances: Vec<I>,
     ) -> Self {
         let render_data = {
             let vertex_buffer = renderer.create_vertex_buffer(bytemuck::cast_slice(&vertices));
@@ -95,7 +96,7 @@ impl AgeObject {
         }
     }
 
-    pub fn quad(renderer: &mut dyn Renderer, age: f32, instances: Vec<Instance>) -> Self {
+    pub fn quad(renderer: &mut dyn Renderer, age: f32, instances: Vec<I>) -> Self {
         let (vertices, indices) = quad_data();
         let vertices = vertices
             .into_iter()
@@ -110,7 +111,7 @@ impl AgeObject {
     }
 }
 
-impl Renderable for AgeObject {
+impl<I: bytemuck::Pod> Renderable for AgeObject<I> {
     fn num_instances(&self) -> u32 {
         self.previous_instances_len as u32
     }
@@ -0,0 +1,108 @@
+use std::{fmt::Write, str::FromStr};
+
+use jandering_engine::types::Mat4;
+
+use crate::color_obj::AgeVertex;
+
+#[derive(Debug, Clone)]
+pub struct ExportMesh {
+    // the plant's instance transform, so the export matches what's on screen
+    pub transform: Mat4,
+    pub vertices: Vec<AgeVertex>,
+    pub indices: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum MeshFormat {
+    Obj,
+    Ply,
+}
+
+impl FromStr for MeshFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "obj" => Ok(Self::Obj),
+            "ply" => Ok(Self::Ply),
+            _ => Err(format!("unknown mesh format \"{s}\", expected obj or ply")),
+        }
+    }
+}
+
+pub fn export(meshes: &[ExportMesh], format: MeshFormat) -> Vec<u8> {
+    match format {
+        MeshFormat::Obj => to_obj(meshes),
+        MeshFormat::Ply => to_ply(meshes),
+    }
+}
+
+fn to_obj(meshes: &[ExportMesh]) -> Vec<u8> {
+    let mut out = String::new();
+    let mut index_offset = 1;
+    for (i, mesh) in meshes.iter().enumerate() {
+        let _ = writeln!(out, "o plant_{i}");
+        let normals = mesh.transform.inverse().transpose();
+        for v in mesh.vertices.iter() {
+            let p = mesh.transform.transform_point3(v.position);
+            let _ = writeln!(out, "v {} {} {}", p.x, p.y, p.z);
+        }
+        for v in mesh.vertices.iter() {
+            let n = normals.transform_vector3(v.normal).normalize_or_zero();
+            let _ = writeln!(out, "vn {} {} {}", n.x, n.y, n.z);
+        }
+        for face in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [face[0], face[1], face[2]].map(|e| e + index_offset);
+            let _ = writeln!(out, "f {a}//{a} {b}//{b} {c}//{c}");
+        }
+        index_offset += mesh.vertices.len() as u32;
+    }
+    out.into_bytes()
+}
+
+// binary ply, keeps the age of every vertex as an extra property
+fn to_ply(meshes: &[ExportMesh]) -> Vec<u8> {
+    let n_vertices = meshes.iter().map(|e| e.vertices.len()).sum::<usize>();
+    let n_faces = meshes.iter().map(|e| e.indices.len() / 3).sum::<usize>();
+
+    let mut out = format!(
+        "ply\n\
+         format binary_little_endian 1.0\n\
+         element vertex {n_vertices}\n\
+         property float x\n\
+         property float y\n\
+         property float z\n\
+         property float nx\n\
+         property float ny\n\
+         property float nz\n\
+         property float age\n\
+         element face {n_faces}\n\
+         property list uchar uint vertex_indices\n\
+         end_header\n"
+    )
+    .into_bytes();
+
+    for mesh in meshes {
+        let normals = mesh.transform.inverse().transpose();
+        for v in mesh.vertices.iter() {
+            let p = mesh.transform.transform_point3(v.position);
+            let n = normals.transform_vector3(v.normal).normalize_or_zero();
+            for val in [p.x, p.y, p.z, n.x, n.y, n.z, v.age] {
+                out.extend_from_slice(&val.to_le_bytes());
+            }
+        }
+    }
+
+    let mut index_offset = 0;
+    for mesh in meshes {
+        for face in mesh.indices.chunks_exact(3) {
+            out.push(3);
+            for index in face {
+                out.extend_from_slice(&(index + index_offset).to_le_bytes());
+            }
+        }
+        index_offset += mesh.vertices.len() as u32;
+    }
+
+    out
+}
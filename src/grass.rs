@@ -0,0 +1,105 @@
+use jandering_engine::{
+    core::{
+        renderer::Renderer,
+        shader::{
+            BufferLayout, BufferLayoutEntry, BufferLayoutEntryDataType, BufferLayoutStepMode,
+        },
+    },
+    types::Vec3,
+};
+
+use crate::color_obj::{AgeObject, AgeVertex};
+
+// segments along a blade, so the wind bends it instead of shearing a flat quad
+const SEGMENTS: u32 = 4;
+
+// one blade, the shader builds its shape from these
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug, Default)]
+pub struct GrassInstance {
+    // of the root, on the terrain
+    pub position: Vec3,
+    pub height: f32,
+    pub width: f32,
+    // how far the tip leans over when there's no wind, in radians
+    pub bend: f32,
+    // added to the height along the blade when looking up the lut
+    pub color_offset: f32,
+    // goes from 0 to 1, picks which way the blade faces
+    pub seed: f32,
+}
+
+impl GrassInstance {
+    pub fn desc() -> BufferLayout {
+        BufferLayout {
+            step_mode: BufferLayoutStepMode::Instance,
+            entries: &[
+                BufferLayoutEntry {
+                    location: 5,
+                    data_type: BufferLayoutEntryDataType::Float32x3,
+                },
+                BufferLayoutEntry {
+                    location: 6,
+                    data_type: BufferLayoutEntryDataType::Float32,
+                },
+                BufferLayoutEntry {
+                    location: 7,
+                    data_type: BufferLayoutEntryDataType::Float32,
+                },
+                BufferLayoutEntry {
+                    location: 8,
+                    data_type: BufferLayoutEntryDataType::Float32,
+                },
+                BufferLayoutEntry {
+                    location: 9,
+                    data_type: BufferLayoutEntryDataType::Float32,
+                },
+                BufferLayoutEntry {
+                    location: 10,
+                    data_type: BufferLayoutEntryDataType::Float32,
+                },
+            ],
+        }
+    }
+}
+
+impl AgeObject<GrassInstance> {
+    pub fn blades(renderer: &mut dyn Renderer, instances: Vec<GrassInstance>) -> Self {
+        let (vertices, indices) = blade_data();
+        Self::new(renderer, vertices, indices, instances)
+    }
+}
+
+// a unit blade narrowing from the root to a point at the tip,
+// age goes from 0 at the root to 1 at the tip
+fn blade_data() -> (Vec<AgeVertex>, Vec<u32>) {
+    let mut vertices = Vec::new();
+    for i in 0..SEGMENTS {
+        let t = i as f32 / SEGMENTS as f32;
+        let half_width = (1.0 - t) * 0.5;
+        for x in [-half_width, half_width] {
+            vertices.push(AgeVertex {
+                position: Vec3::new(x, t, 0.0),
+                normal: Vec3::Z,
+                age: t,
+                ..Default::default()
+            });
+        }
+    }
+    vertices.push(AgeVertex {
+        position: Vec3::Y,
+        normal: Vec3::Z,
+        age: 1.0,
+        ..Default::default()
+    });
+
+    let mut indices = Vec::new();
+    for i in 0..SEGMENTS - 1 {
+        let a = i * 2;
+        indices.extend_from_slice(&[a, a + 1, a + 2, a + 1, a + 3, a + 2]);
+    }
+    let last = (SEGMENTS - 1) * 2;
+    indices.extend_from_slice(&[last, last + 1, last + 2]);
+
+    (vertices, indices)
+}
@@ -1,8 +1,14 @@
-use std::ops::{Add, Rem, Sub};
-
 use image::Rgb32FImage;
-use jandering_engine::types::Vec2;
+use jandering_engine::types::{Vec2, Vec3, Vec4};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Filter {
+    Bilinear,
+    // catmull-rom over the 4x4 texels around the sample, smoother but 4 times the reads
+    Bicubic,
+}
 
+// sampled like a repeating texture with texel centers at half texels, the same way the gpu does
 pub struct Image {
     image: Rgb32FImage,
     width: u32,
@@ -22,39 +28,175 @@ impl Image {
         }
     }
 
+    // the first channel, bilinear
     pub fn sample(&self, u: f32, v: f32) -> f32 {
-        let u = wrap(u * self.scale, 0.0, 1.0) * self.width as f32;
-        let v = wrap(v * self.scale, 0.0, 1.0) * self.height as f32;
-        let x = u as i32;
-        let y = v as i32;
+        self.sample_rgb(u, v, Filter::Bilinear).x
+    }
+
+    pub fn sample_bicubic(&self, u: f32, v: f32) -> f32 {
+        self.sample_rgb(u, v, Filter::Bicubic).x
+    }
 
-        let mut vals = Vec::with_capacity(9);
+    pub fn sample_rgb(&self, u: f32, v: f32, filter: Filter) -> Vec3 {
+        self.sample_rgba(u, v, filter).truncate()
+    }
 
-        for i in -1..=1 {
-            let y = wrap(y + i, 0, self.height as i32 - 1) as u32;
-            for j in -1..=1 {
-                let x = wrap(x + j, 0, self.width as i32 - 1) as u32;
-                let val = self.image.get_pixel(x, y)[0];
-                let dist = (Vec2::new(j as f32, i as f32) * 0.5).distance(Vec2::new(u, v).fract());
+    // the image has no alpha, so it's always 1
+    pub fn sample_rgba(&self, u: f32, v: f32, filter: Filter) -> Vec4 {
+        let x = u * self.scale * self.width as f32 - 0.5;
+        let y = v * self.scale * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
 
-                vals.push(((1.0 - dist.min(1.0)) * val, val))
+        match filter {
+            Filter::Bilinear => {
+                let top = self.texel(x0, y0).lerp(self.texel(x0 + 1, y0), tx);
+                let bottom = self.texel(x0, y0 + 1).lerp(self.texel(x0 + 1, y0 + 1), tx);
+                top.lerp(bottom, ty)
+            }
+            Filter::Bicubic => {
+                let wx = catmull_rom(tx);
+                let wy = catmull_rom(ty);
+                let mut sum = Vec4::ZERO;
+                for (j, wy) in wy.iter().enumerate() {
+                    let mut row = Vec4::ZERO;
+                    for (i, wx) in wx.iter().enumerate() {
+                        row += self.texel(x0 + i as i32 - 1, y0 + j as i32 - 1) * *wx;
+                    }
+                    sum += row * *wy;
+                }
+                sum
             }
         }
+    }
+
+    // rate of change of the first channel per world unit, along u and v, bicubic so it doesn't
+    // jump at every texel the way a bilinear slope does
+    pub fn gradient(&self, u: f32, v: f32) -> Vec2 {
+        let du = 1.0 / (self.width as f32 * self.scale);
+        let dv = 1.0 / (self.height as f32 * self.scale);
+        Vec2::new(
+            (self.sample_bicubic(u + du, v) - self.sample_bicubic(u - du, v)) / (2.0 * du),
+            (self.sample_bicubic(u, v + dv) - self.sample_bicubic(u, v - dv)) / (2.0 * dv),
+        )
+    }
+
+    // treating the first channel as a heightmap `height` units tall, y is up
+    pub fn normal(&self, u: f32, v: f32, height: f32) -> Vec3 {
+        let gradient = self.gradient(u, v) * height;
+        Vec3::new(-gradient.x, 1.0, -gradient.y).normalize()
+    }
 
-        let sum = vals.iter().fold(0.0, |acc, (e, _)| acc + e);
-        vals.into_iter().map(|(e, val)| (e / sum) * val).sum()
+    fn texel(&self, x: i32, y: i32) -> Vec4 {
+        let x = x.rem_euclid(self.width as i32) as u32;
+        let y = y.rem_euclid(self.height as i32) as u32;
+        let pixel = self.image.get_pixel(x, y);
+        Vec4::new(pixel[0], pixel[1], pixel[2], 1.0)
     }
 }
 
-fn wrap<T>(val: T, min: T, max: T) -> T
-where
-    T: Copy + PartialEq + PartialOrd + Sub<Output = T> + Rem<Output = T> + Add<Output = T>,
-{
-    if val < min {
-        max + ((val - min) % (max - min))
-    } else if val > max {
-        min + ((val - max) % (max - min))
-    } else {
-        val
+fn catmull_rom(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        (-t3 + 2.0 * t2 - t) * 0.5,
+        (3.0 * t3 - 5.0 * t2 + 2.0) * 0.5,
+        (-3.0 * t3 + 4.0 * t2 + t) * 0.5,
+        (t3 - t2) * 0.5,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, Rgb32FImage};
+
+    use super::{Filter, Image};
+
+    const SIZE: u32 = 16;
+
+    fn image(scale: f32, f: impl Fn(u32, u32) -> f32) -> Image {
+        let image = Rgb32FImage::from_fn(SIZE, SIZE, |x, y| {
+            let value = f(x, y);
+            Rgb([value, value, value])
+        });
+        Image::new(image, scale)
+    }
+
+    // a ramp along u that doesn't wrap within the texels used
+    fn ramp(scale: f32) -> Image {
+        image(scale, |x, _| x as f32)
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn texel_centers_are_exact() {
+        let image = image(1.0, |x, y| (x * SIZE + y) as f32);
+        let size = SIZE as f32;
+        for x in 0..SIZE {
+            for y in 0..SIZE {
+                let (u, v) = ((x as f32 + 0.5) / size, (y as f32 + 0.5) / size);
+                let expected = (x * SIZE + y) as f32;
+                assert_close(image.sample(u, v), expected);
+                assert_close(image.sample_bicubic(u, v), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn wraps_around_the_edges() {
+        let image = ramp(0.1);
+        // halfway between the last texel and the first
+        assert_close(image.sample(0.0, 0.5), (SIZE - 1) as f32 * 0.5);
+        // a whole image further along is the same spot
+        let period = 1.0 / 0.1;
+        for u in [0.3, 2.7, 9.9] {
+            assert_close(image.sample(u, 1.0), image.sample(u + period, 1.0));
+            assert_close(image.sample(u, 1.0), image.sample(u - period, 1.0 - period));
+        }
+    }
+
+    #[test]
+    fn filters_agree_on_constants() {
+        let image = image(0.1, |_, _| 0.25);
+        for (u, v) in [(0.0, 0.0), (1.3, 7.1), (-4.2, 12.5)] {
+            assert_close(image.sample(u, v), 0.25);
+            assert_close(image.sample_bicubic(u, v), 0.25);
+        }
+    }
+
+    #[test]
+    fn filters_agree_on_ramps() {
+        let image = ramp(1.0);
+        let size = SIZE as f32;
+        // catmull-rom is exact on straight lines, as long as all 4 texels are on the ramp
+        for x in [2.0, 3.3, 7.5, 12.9] {
+            let u = (x + 0.5) / size;
+            let bilinear = image.sample_rgb(u, 0.5, Filter::Bilinear).x;
+            let bicubic = image.sample_rgb(u, 0.5, Filter::Bicubic).x;
+            assert_close(bilinear, x);
+            assert_close(bicubic, x);
+        }
+    }
+
+    #[test]
+    fn gradient_of_a_ramp() {
+        let scale = 0.1;
+        let image = ramp(scale);
+        // one texel is 1 / (SIZE * scale) world units and the ramp climbs 1 per texel
+        let slope = SIZE as f32 * scale;
+        for u in [3.0, 5.0, 8.0] {
+            let gradient = image.gradient(u, 2.0);
+            assert_close(gradient.x, slope);
+            assert_close(gradient.y, 0.0);
+        }
+
+        let normal = image.normal(5.0, 2.0, 1.0);
+        assert_close(normal.length(), 1.0);
+        assert_close(normal.z, 0.0);
+        assert!(normal.x < 0.0 && normal.y > 0.0);
     }
 }
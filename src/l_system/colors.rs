@@ -1,32 +1,194 @@
 use jandering_engine::types::Vec3;
 
-pub fn parse_colors(colors: &[(u32, Vec3)]) -> Vec<Vec3> {
-    if let Some(last) = colors.last() {
-        let n_colors = last.0;
-        let mut color_lut = Vec::with_capacity(n_colors as usize);
-
-        let mut current_color_i = 0;
-        for i in 0..=n_colors {
-            let current_color = &colors[current_color_i];
-            let color = if let Some(next) = colors.iter().find(|e| e.0 > current_color.0) {
-                if next.0 == i {
-                    current_color_i += 1;
-                }
-
-                let t = (i - current_color.0) as f32 / (next.0 - current_color.0) as f32;
-                Vec3::from(current_color.1) * (1.0 - t) + Vec3::from(next.1) * t
+#[derive(Debug, Clone, Copy)]
+pub struct ColorStop {
+    pub position: f32,
+    pub color: Vec3,
+}
+
+impl ColorStop {
+    pub fn new(position: f32, color: Vec3) -> Self {
+        Self { position, color }
+    }
+}
+
+// samples `resolution` evenly spaced colors over 0..=1
+// stops may come in any order, stops sharing a position form a hard edge
+pub fn build_gradient(stops: &[ColorStop], resolution: usize) -> Vec<Vec3> {
+    let stops = sorted_stops(stops);
+    if stops.is_empty() || resolution == 0 {
+        return Vec::new();
+    }
+
+    (0..resolution)
+        .map(|i| {
+            let t = if resolution > 1 {
+                i as f32 / (resolution - 1) as f32
             } else {
-                Vec3::from(current_color.1)
+                0.0
             };
-            color_lut.push(color);
+            sample_gradient(&stops, t)
+        })
+        .collect()
+}
+
+fn sample_gradient(stops: &[ColorStop], t: f32) -> Vec3 {
+    // last stop at or before t, so the later of two duplicate stops wins
+    let current = match stops.iter().rposition(|e| e.position <= t) {
+        Some(i) => i,
+        None => return stops[0].color,
+    };
+
+    match stops.get(current + 1) {
+        Some(next) => {
+            let current = &stops[current];
+            let t = (t - current.position) / (next.position - current.position);
+            current.color * (1.0 - t) + next.color * t
         }
+        None => stops[current].color,
+    }
+}
+
+fn sorted_stops(stops: &[ColorStop]) -> Vec<ColorStop> {
+    let mut stops = stops
+        .iter()
+        .filter(|e| e.position.is_finite())
+        .map(|e| ColorStop::new(e.position.clamp(0.0, 1.0), e.color))
+        .collect::<Vec<_>>();
+    // stable, so duplicates keep the order they were given in
+    stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+    stops
+}
 
-        color_lut
-    } else {
-        Vec::new()
+// ages are integer stops, the lut gets one texel per age up to the highest one
+pub fn parse_colors(colors: &[(u32, Vec3)]) -> Vec<Vec3> {
+    match colors.iter().map(|(age, _)| *age).max() {
+        Some(n_colors) => parse_colors_with_resolution(colors, n_colors as usize + 1),
+        None => Vec::new(),
     }
 }
 
+// same as parse_colors but resampled to `resolution` texels, so several palettes can share a lut
+pub fn parse_colors_with_resolution(colors: &[(u32, Vec3)], resolution: usize) -> Vec<Vec3> {
+    let Some(n_colors) = colors.iter().map(|(age, _)| *age).max() else {
+        return Vec::new();
+    };
+
+    let stops = colors
+        .iter()
+        .map(|(age, color)| {
+            let position = if n_colors == 0 {
+                0.0
+            } else {
+                *age as f32 / n_colors as f32
+            };
+            ColorStop::new(position, *color)
+        })
+        .collect::<Vec<_>>();
+
+    build_gradient(&stops, resolution)
+}
+
 pub fn parse_colors_linear(colors: &[(u32, Vec3)]) -> Vec<Vec3> {
-    colors.iter().map(|(_, color)| *color).collect()
+    let mut colors = colors.to_vec();
+    colors.sort_by_key(|(age, _)| *age);
+    colors.into_iter().map(|(_, color)| color).collect()
+}
+
+// the colors of parse_colors_linear spread evenly over `resolution` texels
+pub fn parse_colors_linear_with_resolution(colors: &[(u32, Vec3)], resolution: usize) -> Vec<Vec3> {
+    let colors = parse_colors_linear(colors);
+    let stops = colors
+        .iter()
+        .enumerate()
+        .map(|(i, color)| {
+            let position = if colors.len() > 1 {
+                i as f32 / (colors.len() - 1) as f32
+            } else {
+                0.0
+            };
+            ColorStop::new(position, *color)
+        })
+        .collect::<Vec<_>>();
+
+    build_gradient(&stops, resolution)
+}
+
+#[cfg(test)]
+mod tests {
+    use jandering_engine::types::Vec3;
+
+    use super::{build_gradient, sample_gradient, sorted_stops, ColorStop};
+
+    const BLACK: Vec3 = Vec3::ZERO;
+    const WHITE: Vec3 = Vec3::ONE;
+    const RED: Vec3 = Vec3::X;
+    const BLUE: Vec3 = Vec3::Z;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{a} != {b}");
+    }
+
+    #[test]
+    fn sorts_clamps_and_drops_invalid_stops() {
+        let stops = sorted_stops(&[
+            ColorStop::new(0.8, WHITE),
+            ColorStop::new(f32::NAN, RED),
+            ColorStop::new(-1.0, BLACK),
+            ColorStop::new(0.3, BLUE),
+            ColorStop::new(f32::INFINITY, RED),
+        ]);
+        let positions = stops.iter().map(|e| e.position).collect::<Vec<_>>();
+        assert_eq!(positions, vec![0.0, 0.3, 0.8]);
+        assert_eq!(stops[0].color, BLACK);
+    }
+
+    #[test]
+    fn unsorted_stops() {
+        let gradient = build_gradient(&[ColorStop::new(1.0, WHITE), ColorStop::new(0.0, BLACK)], 3);
+        assert_eq!(gradient.len(), 3);
+        assert_close(gradient[0], BLACK);
+        assert_close(gradient[1], Vec3::splat(0.5));
+        assert_close(gradient[2], WHITE);
+    }
+
+    #[test]
+    fn duplicate_positions_make_a_hard_edge() {
+        let stops = sorted_stops(&[
+            ColorStop::new(0.0, BLACK),
+            ColorStop::new(0.5, RED),
+            ColorStop::new(0.5, BLUE),
+            ColorStop::new(1.0, WHITE),
+        ]);
+        // duplicates keep their order, the later one wins at the edge
+        assert_eq!(stops[1].color, RED);
+        assert_eq!(stops[2].color, BLUE);
+        assert_close(sample_gradient(&stops, 0.25), RED * 0.5);
+        assert_close(sample_gradient(&stops, 0.5), BLUE);
+        assert_close(sample_gradient(&stops, 0.75), (BLUE + WHITE) * 0.5);
+    }
+
+    #[test]
+    fn single_stop() {
+        let stops = [ColorStop::new(0.3, RED)];
+        assert_eq!(build_gradient(&stops, 4), vec![RED; 4]);
+        assert_eq!(build_gradient(&stops, 1), vec![RED]);
+        assert!(build_gradient(&stops, 0).is_empty());
+        assert!(build_gradient(&[], 4).is_empty());
+    }
+
+    #[test]
+    fn stops_on_one_position_dont_divide_by_zero() {
+        let stops = [ColorStop::new(0.5, RED), ColorStop::new(0.5, BLUE)];
+        let gradient = build_gradient(&stops, 5);
+        assert!(gradient.iter().all(|e| e.is_finite()));
+        assert_close(gradient[0], RED);
+        assert_close(gradient[2], BLUE);
+        assert_close(gradient[4], BLUE);
+
+        // every stop past the end
+        let stops = sorted_stops(&[ColorStop::new(1.0, RED), ColorStop::new(1.0, BLUE)]);
+        assert_close(sample_gradient(&stops, 0.0), RED);
+        assert_close(sample_gradient(&stops, 1.0), BLUE);
+    }
 }
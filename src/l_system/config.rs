@@ -1,17 +1,18 @@
-use std::collections::HashMap;
+use std::{collections::BTreeMap, fmt};
 
 use is_none_or::IsNoneOr;
-use rand::{rngs::ThreadRng, Rng};
+use rand::{rngs::StdRng, Rng};
+use serde::Serialize;
 
 use super::RenderConfig;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Range { min: f32, max: f32 },
     Exact(f32),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Values {
     Multiple(Vec<Value>),
     Exact(Value),
@@ -53,7 +54,8 @@ impl Values {
                         .collect::<Vec<_>>();
 
                     chars.nth(j);
-                    return if values.len() == 1 {
+                    // a trailing comma keeps a single value a list, see Display
+                    return if values.len() == 1 && !string.contains(',') {
                         Self::Exact(values[0].clone())
                     } else {
                         Self::Multiple(values)
@@ -71,7 +73,7 @@ impl Values {
         Self::Default
     }
 
-    pub fn get(&self, default: f32, rng: &mut ThreadRng) -> f32 {
+    pub fn get(&self, default: f32, rng: &mut StdRng) -> f32 {
         let val = match self {
             Values::Multiple(vec) => {
                 let i = rng.gen_range(0..vec.len());
@@ -88,7 +90,15 @@ impl Values {
     }
 }
 
-#[derive(Debug, Clone)]
+// `\` and `/` can also be written as `<` and `>`, whichever was used is written back out
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Spelling {
+    #[default]
+    Slash,
+    Angle,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum LSymbol {
     Scope,
     ScopeEnd,
@@ -98,12 +108,12 @@ pub enum LSymbol {
     RotateNegX(Values),
     RotateY(Values),
     RotateNegY(Values),
-    RotateZ(Values),
-    RotateNegZ(Values),
+    RotateZ(Values, Spelling),
+    RotateNegZ(Values, Spelling),
     Scale(Values),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LRule {
     pub result: Vec<LSymbol>,
     pub chance: f32,
@@ -111,67 +121,156 @@ pub struct LRule {
     pub max_gen: Option<f32>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LRuleSet {
     pub chance: f32,
     pub rules: Vec<LRule>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LRuleSets {
     current: usize,
-    sets: Vec<LRuleSet>,
+    pub sets: Vec<LRuleSet>,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct LSystemBuildConfig {
     pub iterations: u32,
+    // grammars tuned for a set number of iterations lose their shape past it
+    pub max_iterations: Option<u32>,
     pub initial: Vec<LSymbol>,
-    pub rule_sets: HashMap<char, LRuleSets>,
+    pub rule_sets: BTreeMap<char, LRuleSets>,
+}
+
+impl LSystemBuildConfig {
+    // returns false when the count had to be clamped
+    pub fn set_iterations(&mut self, iterations: u32) -> bool {
+        self.iterations = self
+            .max_iterations
+            .map_or(iterations, |max| iterations.min(max));
+        self.iterations == iterations
+    }
 }
 
-#[derive(Default)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct LConfig {
     pub rendering: RenderConfig,
     pub rules: LSystemBuildConfig,
 }
 
+// floats always keep a decimal point, `50` would still parse but `50.0` is what presets use
+fn fmt_float(f: &mut fmt::Formatter<'_>, value: f32) -> fmt::Result {
+    if value.fract() == 0.0 && value.is_finite() {
+        write!(f, "{value:.1}")
+    } else {
+        write!(f, "{value}")
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Range { min, max } => {
+                fmt_float(f, *min)?;
+                write!(f, "~")?;
+                fmt_float(f, *max)
+            }
+            Value::Exact(value) => fmt_float(f, *value),
+        }
+    }
+}
+
+impl fmt::Display for Values {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Values::Multiple(values) => {
+                write!(f, "(")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                // otherwise a list of one reads back as an exact value
+                if values.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Values::Exact(value) => write!(f, "({value})"),
+            Values::Default => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for LSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LSymbol::Scope => write!(f, "["),
+            LSymbol::ScopeEnd => write!(f, "]"),
+            LSymbol::Rule(id) | LSymbol::Object { id, .. } => write!(f, "{id}"),
+            LSymbol::RotateY(values) => write!(f, "+{values}"),
+            LSymbol::RotateNegY(values) => write!(f, "-{values}"),
+            LSymbol::RotateX(values) => write!(f, "&{values}"),
+            LSymbol::RotateNegX(values) => write!(f, "^{values}"),
+            LSymbol::RotateZ(values, Spelling::Slash) => write!(f, "\\{values}"),
+            LSymbol::RotateZ(values, Spelling::Angle) => write!(f, "<{values}"),
+            LSymbol::RotateNegZ(values, Spelling::Slash) => write!(f, "/{values}"),
+            LSymbol::RotateNegZ(values, Spelling::Angle) => write!(f, ">{values}"),
+            LSymbol::Scale(values) => write!(f, "|{values}"),
+        }
+    }
+}
+
+pub fn symbols_to_string(symbols: &[LSymbol]) -> String {
+    symbols.iter().map(|e| e.to_string()).collect()
+}
+
+pub fn symbols_from_str(string: &str) -> Vec<LSymbol> {
+    json::string_to_symbols(string.to_string())
+}
+
 mod json {
-    use std::collections::HashMap;
+    use std::collections::BTreeMap;
 
-    use serde::Deserialize;
+    use serde::{Deserialize, Serialize};
 
     use crate::l_system::RenderConfig;
 
-    use super::{LRule, LRuleSet, LRuleSets, LSymbol, LSystemBuildConfig, Values};
+    use super::{
+        symbols_to_string, LConfig, LRule, LRuleSet, LRuleSets, LSymbol, LSystemBuildConfig,
+        Spelling, Values,
+    };
 
-    #[derive(Deserialize, Debug, Clone)]
+    #[derive(Deserialize, Serialize, Debug, Clone)]
     pub(crate) struct RuleJSON {
         pub(crate) result: String,
         #[serde(default)]
         pub(crate) chance: Option<f32>,
-        #[serde(default)]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub(crate) min_gen: Option<f32>,
-        #[serde(default)]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         pub(crate) max_gen: Option<f32>,
     }
 
-    #[derive(Deserialize, Debug, Clone)]
+    #[derive(Deserialize, Serialize, Debug, Clone)]
     pub(crate) struct RuleSetJSON {
         pub(crate) rules: Vec<RuleJSON>,
         #[serde(default)]
         pub(crate) chance: Option<f32>,
     }
 
-    #[derive(Deserialize, Clone)]
+    #[derive(Deserialize, Serialize, Clone)]
     pub(crate) struct LSystemBuildConfigJSON {
         #[serde(default)]
         pub(crate) iterations: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub(crate) max_iterations: Option<u32>,
         pub(crate) initial: String,
-        pub(crate) rules: HashMap<char, Vec<RuleSetJSON>>,
+        pub(crate) rules: BTreeMap<char, Vec<RuleSetJSON>>,
     }
 
-    #[derive(Deserialize)]
+    #[derive(Deserialize, Serialize)]
     pub(crate) struct LConfigJSON {
         pub(crate) rendering: RenderConfig,
         pub(crate) rules: LSystemBuildConfigJSON,
@@ -181,6 +280,7 @@ mod json {
         fn from(val: LSystemBuildConfigJSON) -> Self {
             let LSystemBuildConfigJSON {
                 iterations,
+                max_iterations,
                 initial,
                 rules,
             } = val;
@@ -246,17 +346,63 @@ mod json {
                     };
                     (key, sets)
                 })
-                .collect::<HashMap<char, LRuleSets>>();
+                .collect::<BTreeMap<char, LRuleSets>>();
 
             LSystemBuildConfig {
                 iterations,
+                max_iterations,
                 initial,
                 rule_sets,
             }
         }
     }
 
-    fn string_to_symbols(string: String) -> Vec<LSymbol> {
+    impl From<&LConfig> for LConfigJSON {
+        fn from(val: &LConfig) -> Self {
+            let LSystemBuildConfig {
+                iterations,
+                max_iterations,
+                initial,
+                rule_sets,
+            } = &val.rules;
+
+            let rules = rule_sets
+                .iter()
+                .map(|(key, sets)| {
+                    let sets = sets
+                        .sets
+                        .iter()
+                        .map(|set| RuleSetJSON {
+                            rules: set
+                                .rules
+                                .iter()
+                                .map(|rule| RuleJSON {
+                                    result: symbols_to_string(&rule.result),
+                                    chance: Some(rule.chance),
+                                    min_gen: rule.min_gen,
+                                    max_gen: rule.max_gen,
+                                })
+                                .collect(),
+                            chance: Some(set.chance),
+                        })
+                        .collect();
+                    (*key, sets)
+                })
+                .collect();
+
+            LConfigJSON {
+                rendering: val.rendering.clone(),
+                rules: LSystemBuildConfigJSON {
+                    iterations: *iterations,
+                    max_iterations: *max_iterations,
+                    initial: symbols_to_string(initial),
+                    rules,
+                },
+            }
+        }
+    }
+
+    pub(crate) fn string_to_symbols(string: String) -> Vec<LSymbol> {
         let mut symbols = Vec::with_capacity(string.capacity());
         let mut chars = string.chars().peekable();
 
@@ -271,8 +417,10 @@ mod json {
                         '-' => LSymbol::RotateNegY(values),
                         '&' => LSymbol::RotateX(values),
                         '^' => LSymbol::RotateNegX(values),
-                        '\\' | '<' => LSymbol::RotateZ(values),
-                        '/' | '>' => LSymbol::RotateNegZ(values),
+                        '\\' => LSymbol::RotateZ(values, Spelling::Slash),
+                        '<' => LSymbol::RotateZ(values, Spelling::Angle),
+                        '/' => LSymbol::RotateNegZ(values, Spelling::Slash),
+                        '>' => LSymbol::RotateNegZ(values, Spelling::Angle),
                         '|' => LSymbol::Scale(values),
                         _ => continue,
                     };
@@ -293,6 +441,12 @@ mod json {
     }
 }
 
+impl Serialize for LConfig {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        json::LConfigJSON::from(self).serialize(serializer)
+    }
+}
+
 impl LConfig {
     pub fn from_json(json: String) -> Result<Self, String> {
         match serde_json::from_str::<json::LConfigJSON>(&json) {
@@ -304,14 +458,18 @@ impl LConfig {
         }
     }
 
-    pub fn get_rule(&self, id: &char, rng: &mut ThreadRng, age: f32) -> Option<&[LSymbol]> {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn get_rule(&self, id: &char, rng: &mut StdRng, age: f32) -> Option<&[LSymbol]> {
         self.rules.rule_sets.get(id).and_then(|sets| {
             let rules = &sets.sets[sets.current].rules;
             pick_rule(rules, rng, age)
         })
     }
 
-    pub fn randomize_rule_sets(&mut self, n: Option<u32>, rng: &mut ThreadRng) {
+    pub fn randomize_rule_sets(&mut self, n: Option<u32>, rng: &mut StdRng) {
         if let Some(n) = n {
             let mut indices = self.rules.rule_sets.keys().copied().collect::<Vec<_>>();
             for _ in 0..n.min(indices.len() as u32) {
@@ -331,7 +489,7 @@ impl LConfig {
 
 fn pick_rule<'rules>(
     rules: &'rules [LRule],
-    rng: &mut ThreadRng,
+    rng: &mut StdRng,
     age: f32,
 ) -> Option<&'rules [LSymbol]> {
     let filtered = rules.iter().filter(|rule| {
@@ -351,3 +509,245 @@ fn pick_rule<'rules>(
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{
+        symbols_from_str, symbols_to_string, LConfig, LRule, LRuleSet, LRuleSets, LSymbol,
+        LSystemBuildConfig, Spelling, Value, Values,
+    };
+    use crate::l_system::{
+        generator::{generate, GeneratorKnobs},
+        RenderConfig, Shape,
+    };
+
+    const SYSTEMS: [&str; 8] = [
+        include_str!("../../res/systems/angle 50.json"),
+        include_str!("../../res/systems/grm.json"),
+        include_str!("../../res/systems/initial.json"),
+        include_str!("../../res/systems/myyyb taka grda smreka.json"),
+        include_str!("../../res/systems/normal tree.json"),
+        include_str!("../../res/systems/ta mi je ful usec.json"),
+        include_str!("../../res/systems/tak zanimiv z kotom 45.json"),
+        include_str!("../../res/systems/tko lovkast.json"),
+    ];
+
+    fn assert_round_trip(config: &LConfig) {
+        let json = config.to_json();
+        let read_back = LConfig::from_json(json.clone()).unwrap();
+        assert_eq!(&read_back, config, "{json}");
+        assert_eq!(read_back.to_json(), json);
+    }
+
+    #[test]
+    fn parsed_configs_round_trip() {
+        for system in SYSTEMS {
+            assert_round_trip(&LConfig::from_json(system.to_string()).unwrap());
+        }
+    }
+
+    #[test]
+    fn generated_configs_round_trip() {
+        for seed in 0..20 {
+            let knobs = GeneratorKnobs {
+                bushiness: seed as f32 / 19.0,
+                symmetry: 1.0 - seed as f32 / 19.0,
+                height: (seed % 5) as f32 / 4.0,
+            };
+            assert_round_trip(&generate(seed, &knobs));
+        }
+    }
+
+    // a seed only means something if a reparsed config grows the same plant from it
+    fn seeded_picks(json: &str, seed: u64) -> (Vec<(char, usize)>, Vec<Vec<LSymbol>>) {
+        let mut config = LConfig::from_json(json.to_string()).unwrap();
+        let mut rng = StdRng::seed_from_u64(seed);
+        config.randomize_rule_sets(None, &mut rng);
+        config.randomize_rule_sets(Some(2), &mut rng);
+
+        let current = config
+            .rules
+            .rule_sets
+            .iter()
+            .map(|(id, sets)| (*id, sets.current))
+            .collect::<Vec<_>>();
+        let symbols = config
+            .rules
+            .rule_sets
+            .keys()
+            .flat_map(|id| [0.0, 0.5, 1.0].map(|age| (*id, age)))
+            .map(|(id, age)| {
+                config
+                    .get_rule(&id, &mut rng, age)
+                    .map(<[LSymbol]>::to_vec)
+                    .unwrap_or_default()
+            })
+            .collect();
+        (current, symbols)
+    }
+
+    #[test]
+    fn same_seed_picks_the_same_rules() {
+        for system in SYSTEMS {
+            for seed in [0, 42, 1234] {
+                assert_eq!(seeded_picks(system, seed), seeded_picks(system, seed));
+            }
+        }
+    }
+
+    // floats that are easy to get wrong when written out and read back
+    const EDGE_FLOATS: [f32; 8] = [0.0, -0.0, 50.0, -50.0, 0.001, -123.456, 1e6, f32::EPSILON];
+
+    fn random_float(rng: &mut StdRng) -> f32 {
+        if rng.gen_bool(0.3) {
+            EDGE_FLOATS[rng.gen_range(0..EDGE_FLOATS.len())]
+        } else {
+            rng.gen_range(-360.0..360.0)
+        }
+    }
+
+    fn random_value(rng: &mut StdRng) -> Value {
+        if rng.gen_bool(0.5) {
+            Value::Exact(random_float(rng))
+        } else {
+            Value::Range {
+                min: random_float(rng),
+                max: random_float(rng),
+            }
+        }
+    }
+
+    fn random_values(rng: &mut StdRng) -> Values {
+        match rng.gen_range(0..3) {
+            0 => Values::Default,
+            1 => Values::Exact(random_value(rng)),
+            _ => Values::Multiple(
+                (0..rng.gen_range(1..4))
+                    .map(|_| random_value(rng))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn random_spelling(rng: &mut StdRng) -> Spelling {
+        if rng.gen_bool(0.5) {
+            Spelling::Slash
+        } else {
+            Spelling::Angle
+        }
+    }
+
+    fn random_symbols(rng: &mut StdRng) -> Vec<LSymbol> {
+        (0..rng.gen_range(0..24))
+            .map(|_| match rng.gen_range(0..11) {
+                0 => LSymbol::Scope,
+                1 => LSymbol::ScopeEnd,
+                2 => LSymbol::Rule(rng.gen_range('A'..='Z')),
+                3 => LSymbol::Object {
+                    id: rng.gen_range('a'..='z'),
+                    age: 0,
+                },
+                4 => LSymbol::RotateX(random_values(rng)),
+                5 => LSymbol::RotateNegX(random_values(rng)),
+                6 => LSymbol::RotateY(random_values(rng)),
+                7 => LSymbol::RotateNegY(random_values(rng)),
+                8 => LSymbol::RotateZ(random_values(rng), random_spelling(rng)),
+                9 => LSymbol::RotateNegZ(random_values(rng), random_spelling(rng)),
+                _ => LSymbol::Scale(random_values(rng)),
+            })
+            .collect()
+    }
+
+    fn random_config(rng: &mut StdRng) -> LConfig {
+        let generation = |rng: &mut StdRng| rng.gen_bool(0.5).then(|| rng.gen_range(0.0..1.0));
+        let rule_sets = (0..rng.gen_range(1..5))
+            .map(|_| {
+                let sets = (0..rng.gen_range(1..4))
+                    .map(|_| LRuleSet {
+                        chance: rng.gen_range(0.0..1.0),
+                        rules: (0..rng.gen_range(1..4))
+                            .map(|_| LRule {
+                                result: random_symbols(rng),
+                                chance: rng.gen_range(0.0..1.0),
+                                min_gen: generation(rng),
+                                max_gen: generation(rng),
+                            })
+                            .collect(),
+                    })
+                    .collect();
+                (rng.gen_range('A'..='Z'), LRuleSets { current: 0, sets })
+            })
+            .collect::<BTreeMap<_, _>>();
+        let shapes = (0..rng.gen_range(0..4))
+            .map(|_| {
+                let shape = match rng.gen_range(0..3) {
+                    0 => Shape::Branch {
+                        width: random_float(rng),
+                        length: random_float(rng),
+                    },
+                    1 => Shape::Line {
+                        width: random_float(rng),
+                        length: random_float(rng),
+                    },
+                    _ => Shape::Circle {
+                        size: random_float(rng),
+                    },
+                };
+                (rng.gen_range('a'..='z'), shape)
+            })
+            .collect();
+
+        LConfig {
+            rendering: RenderConfig {
+                default_angle_change: random_float(rng),
+                shapes,
+            },
+            rules: LSystemBuildConfig {
+                iterations: rng.gen_range(0..12),
+                max_iterations: rng.gen_bool(0.5).then(|| rng.gen_range(0..12)),
+                initial: random_symbols(rng),
+                rule_sets,
+            },
+        }
+    }
+
+    #[test]
+    fn random_symbols_round_trip() {
+        let mut rng = StdRng::seed_from_u64(32);
+        for _ in 0..2000 {
+            let symbols = random_symbols(&mut rng);
+            let string = symbols_to_string(&symbols);
+            let read_back = symbols_from_str(&string);
+            assert_eq!(read_back, symbols, "{string}");
+            assert_eq!(symbols_to_string(&read_back), string);
+        }
+    }
+
+    #[test]
+    fn random_configs_round_trip() {
+        let mut rng = StdRng::seed_from_u64(32);
+        for _ in 0..200 {
+            assert_round_trip(&random_config(&mut rng));
+        }
+    }
+
+    #[test]
+    fn symbols_keep_their_spelling() {
+        for string in [
+            ">(-50.0~50.0)^(-50.0~50.0)S",
+            "<(10.0)/(10.0)\\(10.0)",
+            "f+(30.0,)&(1.5,2.0~3.0)|(0.75)",
+            "[+A][-B]|f",
+            "+(-0.0)-(0.0~-0.0)&(-0.0,)",
+            "+-&^\\/<>|",
+            "<(1.5,)>(-2.0~2.0,3.0)",
+            "|(0.001)+(1000000.0)",
+        ] {
+            assert_eq!(symbols_to_string(&symbols_from_str(string)), string);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use rand::{rngs::StdRng, Rng};
+
+use super::config::{LConfig, LSymbol, Spelling, Value, Values};
+
+const MIN_SCALE: f32 = 0.1;
+const MAX_SCALE: f32 = 1.05;
+
+// rate goes from 0 to 1 and is the chance of each kind of mutation happening per production
+pub fn mutate(config: &LConfig, rng: &mut StdRng, rate: f32) -> LConfig {
+    let mut config = config.clone();
+
+    for sets in config.rules.rule_sets.values_mut() {
+        for set in sets.sets.iter_mut() {
+            for rule in set.rules.iter_mut() {
+                mutate_symbols(&mut rule.result, rng, rate);
+                if rng.gen::<f32>() < rate {
+                    rule.chance = (rule.chance * rng.gen_range(0.5..1.5)).max(0.01);
+                }
+            }
+
+            // rules only compete with the ones active over the same generations
+            let totals = set
+                .rules
+                .iter()
+                .map(|rule| {
+                    set.rules
+                        .iter()
+                        .filter(|other| {
+                            (other.min_gen, other.max_gen) == (rule.min_gen, rule.max_gen)
+                        })
+                        .map(|other| other.chance)
+                        .sum::<f32>()
+                })
+                .collect::<Vec<_>>();
+            for (rule, total) in set.rules.iter_mut().zip(totals) {
+                if total > 0.0 {
+                    rule.chance /= total;
+                }
+            }
+        }
+    }
+
+    config
+}
+
+// takes the rules and shapes of both parents, picking one parent per rule id
+pub fn crossover(a: &LConfig, b: &LConfig, rng: &mut StdRng) -> LConfig {
+    let (mut child, other) = if rng.gen_bool(0.5) {
+        (a.clone(), b)
+    } else {
+        (b.clone(), a)
+    };
+
+    for (id, sets) in other.rules.rule_sets.iter() {
+        if !child.rules.rule_sets.contains_key(id) || rng.gen_bool(0.5) {
+            child.rules.rule_sets.insert(*id, sets.clone());
+        }
+    }
+
+    for (id, shape) in other.rendering.shapes.iter() {
+        if !child.rendering.shapes.contains_key(id) || rng.gen_bool(0.5) {
+            child.rendering.shapes.insert(*id, shape.clone());
+        }
+    }
+
+    child
+}
+
+// only rotations and scales get inserted so a mutation can never add new growth
+fn mutate_symbols(symbols: &mut Vec<LSymbol>, rng: &mut StdRng, rate: f32) {
+    for symbol in symbols.iter_mut() {
+        if rng.gen::<f32>() >= rate {
+            continue;
+        }
+        match symbol {
+            LSymbol::RotateX(values)
+            | LSymbol::RotateNegX(values)
+            | LSymbol::RotateY(values)
+            | LSymbol::RotateNegY(values)
+            | LSymbol::RotateZ(values, _)
+            | LSymbol::RotateNegZ(values, _) => {
+                perturb_values(values, rng, 10.0, f32::MIN, f32::MAX)
+            }
+            LSymbol::Scale(values) => perturb_values(values, rng, 0.05, MIN_SCALE, MAX_SCALE),
+            _ => {}
+        }
+    }
+
+    if rng.gen::<f32>() < rate {
+        let angle = rng.gen_range(-30.0..30.0);
+        let values = Values::Exact(Value::Range {
+            min: angle - 5.0,
+            max: angle + 5.0,
+        });
+        let symbol = match rng.gen_range(0..4) {
+            0 => LSymbol::RotateX(values),
+            1 => LSymbol::RotateY(values),
+            2 => LSymbol::RotateZ(values, Spelling::default()),
+            _ => LSymbol::Scale(Values::Exact(Value::Exact(rng.gen_range(0.8..1.0)))),
+        };
+        let i = rng.gen_range(0..=symbols.len());
+        symbols.insert(i, symbol);
+    }
+
+    // scopes stay so brackets remain balanced
+    if rng.gen::<f32>() < rate {
+        let removable = symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| !matches!(e, LSymbol::Scope | LSymbol::ScopeEnd))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if !removable.is_empty() {
+            symbols.remove(removable[rng.gen_range(0..removable.len())]);
+        }
+    }
+}
+
+fn perturb_values(values: &mut Values, rng: &mut StdRng, amount: f32, min: f32, max: f32) {
+    match values {
+        Values::Multiple(values) => values
+            .iter_mut()
+            .for_each(|value| perturb_value(value, rng, amount, min, max)),
+        Values::Exact(value) => perturb_value(value, rng, amount, min, max),
+        Values::Default => {}
+    }
+}
+
+fn perturb_value(value: &mut Value, rng: &mut StdRng, amount: f32, min: f32, max: f32) {
+    *value = match *value {
+        Value::Range {
+            min: range_min,
+            max: range_max,
+        } => {
+            let offset = rng.gen_range(-amount..amount);
+            let half = (range_max - range_min) * 0.5 * rng.gen_range(0.8..1.25);
+            let center = ((range_min + range_max) * 0.5 + offset).clamp(min, max);
+            let (range_min, range_max) = ((center - half).max(min), (center + half).min(max));
+            if range_max - range_min > f32::EPSILON {
+                Value::Range {
+                    min: range_min,
+                    max: range_max,
+                }
+            } else {
+                Value::Exact(center)
+            }
+        }
+        Value::Exact(value) => {
+            Value::Exact((value + rng.gen_range(-amount..amount)).clamp(min, max))
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::mutate;
+    use crate::l_system::generator::{generate, GeneratorKnobs};
+
+    #[test]
+    fn chances_add_up_per_generation_window() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for seed in 0..20 {
+            let config = mutate(&generate(seed, &GeneratorKnobs::default()), &mut rng, 1.0);
+            for sets in config.rules.rule_sets.values() {
+                for set in sets.sets.iter() {
+                    for rule in set.rules.iter() {
+                        let total = set
+                            .rules
+                            .iter()
+                            .filter(|other| {
+                                (other.min_gen, other.max_gen) == (rule.min_gen, rule.max_gen)
+                            })
+                            .map(|other| other.chance)
+                            .sum::<f32>();
+                        assert!((total - 1.0).abs() < 1e-4, "{total}");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::json;
+
+use super::config::{LConfig, Value, Values};
+
+pub const GENERATED_ITERATIONS: u32 = 10;
+
+// upper bound on the number of branch tips a generated plant can have
+const MAX_TIPS: f32 = 1024.0;
+
+// all knobs go from 0 to 1
+#[derive(Debug, Clone, Copy)]
+pub struct GeneratorKnobs {
+    pub bushiness: f32,
+    pub symmetry: f32,
+    pub height: f32,
+}
+
+impl Default for GeneratorKnobs {
+    fn default() -> Self {
+        Self {
+            bushiness: 0.5,
+            symmetry: 0.5,
+            height: 0.5,
+        }
+    }
+}
+
+// the grammar is a trunk rule `T` that grows straight up, a fork rule `A` that splits into
+// branches and a tip rule `L` that ends every branch in a leaf or a blossom
+pub fn generate(seed: u64, knobs: &GeneratorKnobs) -> LConfig {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let bushiness = knobs.bushiness.clamp(0.0, 1.0);
+    let symmetry = knobs.symmetry.clamp(0.0, 1.0);
+    let height = knobs.height.clamp(0.0, 1.0);
+
+    let iterations = GENERATED_ITERATIONS as f32;
+
+    let n_branches = 2 + (bushiness * 2.0).round() as u32 + rng.gen_range(0..=1);
+    let trunk_end = lerp(0.1, 0.4, height) + rng.gen_range(-0.05..0.05);
+
+    // forks can only happen for a whole number of iterations, so the worst case of every rule
+    // picking the fork is n_branches ^ levels tips
+    let max_levels = (MAX_TIPS.ln() / (n_branches as f32).ln()).floor().max(1.0);
+    let levels = ((1.0 - trunk_end) * iterations)
+        .floor()
+        .clamp(1.0, max_levels);
+    // rules keep expanding up to an age of exactly 1, so the tips always get their turn
+    let branch_end = (trunk_end + levels / iterations).min(1.0);
+
+    let fork_chance = lerp(0.5, 0.9, bushiness);
+    let decay = lerp(0.65, 0.85, height) + rng.gen_range(-0.05..0.05);
+    let tilt = lerp(20.0, 60.0, bushiness) * lerp(1.2, 0.7, height);
+
+    let wobble = range(-5.0, 5.0);
+    let trunk = format!("f&{wobble}|(0.97)T");
+
+    let mut fork = format!("f+{}", range(0.0, 360.0));
+    for i in 0..n_branches {
+        let step = 360.0 / n_branches as f32;
+        let jitter = (1.0 - symmetry) * step * 0.5;
+        let azimuth = range(i as f32 * step - jitter, i as f32 * step + jitter);
+        let tilt_jitter = (1.0 - symmetry) * 15.0 + 2.0;
+        let tilt = range(tilt - tilt_jitter, tilt + tilt_jitter);
+        let decay = range(decay - 0.05, decay + 0.05);
+        fork.push_str(&format!("[+{azimuth}&{tilt}|{decay}A]"));
+    }
+    let grow = format!("f&{wobble}|(0.95)A");
+
+    let leaf_angle = range(20.0, 70.0);
+    let has_blossoms = rng.gen_bool(0.5);
+    let tips = if has_blossoms {
+        json!([
+            { "result": format!("[&{leaf_angle}l]"), "chance": 0.7 },
+            { "result": "s", "chance": 0.3 },
+        ])
+    } else {
+        json!([{ "result": format!("[&{leaf_angle}l][^{leaf_angle}l]") }])
+    };
+
+    let config = json!({
+        "rules": {
+            "iterations": GENERATED_ITERATIONS,
+            // more iterations would fit more forks between trunk_end and branch_end
+            "max_iterations": GENERATED_ITERATIONS,
+            "initial": "fT",
+            "rules": {
+                "T": [{ "rules": [
+                    { "result": trunk, "max_gen": trunk_end },
+                    { "result": "A", "min_gen": trunk_end },
+                ] }],
+                "A": [{ "rules": [
+                    { "result": fork, "chance": fork_chance, "max_gen": branch_end },
+                    { "result": grow, "chance": 1.0 - fork_chance, "max_gen": branch_end },
+                    // the windows don't overlap, so this is the only rule left past branch_end
+                    { "result": "L", "chance": 1.0, "min_gen": branch_end },
+                ] }],
+                "L": [{ "rules": tips }],
+            },
+        },
+        "rendering": {
+            "default_angle_change": 15.0,
+            "shapes": {
+                "f": { "Branch": {
+                    "width": rng.gen_range(4.0..8.0),
+                    "length": lerp(0.12, 0.25, height) + rng.gen_range(-0.02..0.02),
+                } },
+                "l": { "Line": {
+                    "width": rng.gen_range(2.0..4.0),
+                    "length": rng.gen_range(0.04..0.1),
+                } },
+                "s": { "Circle": { "size": rng.gen_range(0.03..0.07) } },
+            },
+        },
+    });
+
+    LConfig::from_json(config.to_string()).expect("generated grammar should always be valid")
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+// an empty range would panic when sampled
+fn range(min: f32, max: f32) -> Values {
+    if max - min > f32::EPSILON {
+        Values::Exact(Value::Range { min, max })
+    } else {
+        Values::Exact(Value::Exact(min))
+    }
+}
@@ -1,25 +1,27 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use jandering_engine::types::{Qua, Vec3};
-use rand::rngs::ThreadRng;
-use serde::Deserialize;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
 
 use self::config::{LConfig, LSymbol};
 
 pub mod colors;
 pub mod config;
+pub mod evolution;
+pub mod generator;
 
-#[derive(serde::Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 enum Shape {
     Branch { width: f32, length: f32 },
     Line { width: f32, length: f32 },
     Circle { size: f32 },
 }
 
-#[derive(Deserialize, Clone, Default)]
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq)]
 pub struct RenderConfig {
     default_angle_change: f32,
-    shapes: HashMap<char, Shape>,
+    shapes: BTreeMap<char, Shape>,
 }
 
 #[derive(Debug)]
@@ -64,7 +66,7 @@ impl State {
     }
 }
 
-pub fn build(config: &LConfig, rng: &mut ThreadRng) -> Vec<RenderShape> {
+pub fn build(config: &LConfig, rng: &mut StdRng) -> Vec<RenderShape> {
     let mut states = vec![State {
         scale: 1.0,
         ..Default::default()
@@ -89,7 +91,7 @@ fn build_symbols(
     shapes: &mut Vec<RenderShape>,
     symbols: &[LSymbol],
     config: &LConfig,
-    rng: &mut ThreadRng,
+    rng: &mut StdRng,
     iteration: u32,
 ) {
     let age = iteration as f32 / config.rules.iterations as f32;
@@ -99,8 +101,8 @@ fn build_symbols(
         LSymbol::RotateNegY(_) => -Vec3::Y,
         LSymbol::RotateX(_) => Vec3::X,
         LSymbol::RotateNegX(_) => -Vec3::X,
-        LSymbol::RotateZ(_) => Vec3::Z,
-        LSymbol::RotateNegZ(_) => -Vec3::Z,
+        LSymbol::RotateZ(..) => Vec3::Z,
+        LSymbol::RotateNegZ(..) => -Vec3::Z,
         _ => Vec3::ZERO,
     };
 
@@ -125,8 +127,8 @@ fn build_symbols(
             | LSymbol::RotateNegX(values)
             | LSymbol::RotateY(values)
             | LSymbol::RotateNegY(values)
-            | LSymbol::RotateZ(values)
-            | LSymbol::RotateNegZ(values) => {
+            | LSymbol::RotateZ(values, _)
+            | LSymbol::RotateNegZ(values, _) => {
                 let angle = values.get(config.rendering.default_angle_change, rng);
                 states.last_mut().unwrap().rotation *=
                     Qua::from_axis_angle(symbol_to_axis(symbol), angle.to_radians());
@@ -1,14 +1,19 @@
 use application::Application;
 use jandering_engine::core::{engine::EngineBuilder, window::WindowBuilder};
 
+mod api;
 mod application;
 mod camera_controller;
 mod color_obj;
 mod cylinder;
+mod export;
+mod grass;
 mod icosphere;
 mod image;
 mod l_system;
+mod noise;
 mod render_data;
+mod storage;
 mod timer;
 
 use wasm_bindgen::prelude::*;
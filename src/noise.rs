@@ -0,0 +1,506 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::image::Image;
+
+// everything that can be sampled over the ground plane, output goes from 0 to 1
+pub trait Noise {
+    fn sample(&self, x: f32, z: f32) -> f32;
+}
+
+impl Noise for Image {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        Image::sample(self, x, z)
+    }
+}
+
+// gradient noise on hashed lattice gradients, so it never repeats
+pub struct Perlin {
+    pub seed: u64,
+}
+
+impl Noise for Perlin {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        let (x0, z0) = (x.floor(), z.floor());
+        let (fx, fz) = (x - x0, z - z0);
+        let (x0, z0) = (x0 as i32, z0 as i32);
+
+        let a = gradient(hash(x0, z0, self.seed), fx, fz);
+        let b = gradient(hash(x0 + 1, z0, self.seed), fx - 1.0, fz);
+        let c = gradient(hash(x0, z0 + 1, self.seed), fx, fz - 1.0);
+        let d = gradient(hash(x0 + 1, z0 + 1, self.seed), fx - 1.0, fz - 1.0);
+
+        let (u, v) = (fade(fx), fade(fz));
+        let value = lerp(lerp(a, b, u), lerp(c, d, u), v);
+        (value * 0.5 + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+pub struct Simplex {
+    pub seed: u64,
+}
+
+impl Noise for Simplex {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        const F2: f32 = 0.366_025_42;
+        const G2: f32 = 0.211_324_87;
+
+        let s = (x + z) * F2;
+        let (i, j) = ((x + s).floor(), (z + s).floor());
+        let t = (i + j) * G2;
+        let (x0, z0) = (x - (i - t), z - (j - t));
+        let (i, j) = (i as i32, j as i32);
+
+        let (i1, j1) = if x0 > z0 { (1, 0) } else { (0, 1) };
+        let corners = [
+            (x0, z0, 0, 0),
+            (x0 - i1 as f32 + G2, z0 - j1 as f32 + G2, i1, j1),
+            (x0 - 1.0 + 2.0 * G2, z0 - 1.0 + 2.0 * G2, 1, 1),
+        ];
+
+        let value = corners
+            .iter()
+            .map(|(x, z, di, dj)| {
+                let t = 0.5 - x * x - z * z;
+                if t < 0.0 {
+                    0.0
+                } else {
+                    t.powi(4) * gradient(hash(i + di, j + dj, self.seed), *x, *z)
+                }
+            })
+            .sum::<f32>();
+        (value * 70.0 * 0.5 + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+// distance to the closest of one random point per cell
+pub struct Worley {
+    pub seed: u64,
+}
+
+impl Noise for Worley {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        let (cx, cz) = (x.floor() as i32, z.floor() as i32);
+        let mut closest = f32::MAX;
+        for i in -1..=1 {
+            for j in -1..=1 {
+                let h = hash(cx + i, cz + j, self.seed);
+                let px = (cx + i) as f32 + (h & 0xFFFF) as f32 / 65535.0;
+                let pz = (cz + j) as f32 + ((h >> 16) & 0xFFFF) as f32 / 65535.0;
+                let dist = ((px - x).powi(2) + (pz - z).powi(2)).sqrt();
+                closest = closest.min(dist);
+            }
+        }
+        closest.min(1.0)
+    }
+}
+
+// octaves of the source added together, each at a higher frequency and lower amplitude
+pub struct Fbm<N: Noise> {
+    pub source: N,
+    pub octaves: u32,
+    pub frequency: f32,
+    pub lacunarity: f32,
+    pub gain: f32,
+}
+
+impl<N: Noise> Fbm<N> {
+    pub fn new(source: N, octaves: u32, frequency: f32) -> Self {
+        Self {
+            source,
+            octaves,
+            frequency,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+impl<N: Noise> Noise for Fbm<N> {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        let mut frequency = self.frequency;
+        let mut amplitude = 1.0;
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        for octave in 0..self.octaves.max(1) {
+            // shifted so the octaves don't line up at the origin
+            let offset = octave as f32 * 17.31;
+            let value = self
+                .source
+                .sample(x * frequency + offset, z * frequency - offset);
+            sum += (value * 2.0 - 1.0) * amplitude;
+            total += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        (sum / total * 0.5 + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+// samples the source at a position pushed around by another noise
+pub struct DomainWarp<N: Noise, W: Noise> {
+    pub source: N,
+    pub warp: W,
+    pub strength: f32,
+}
+
+impl<N: Noise, W: Noise> Noise for DomainWarp<N, W> {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        let dx = (self.warp.sample(x, z) * 2.0 - 1.0) * self.strength;
+        let dz = (self.warp.sample(x + 5.2, z + 1.3) * 2.0 - 1.0) * self.strength;
+        self.source.sample(x + dx, z + dz)
+    }
+}
+
+// value noise on a 32 bit hash, simple enough to do exactly the same in the shader, which has no
+// 64 bit integers, matches value_noise in the shader
+pub struct ShaderValue {
+    pub seed: u32,
+}
+
+impl Noise for ShaderValue {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        let (x0, z0) = (x.floor(), z.floor());
+        let (tx, tz) = (smooth(x - x0), smooth(z - z0));
+        let (x0, z0) = (x0 as i32, z0 as i32);
+
+        let a = unit32(hash32(x0, z0, self.seed));
+        let b = unit32(hash32(x0 + 1, z0, self.seed));
+        let c = unit32(hash32(x0, z0 + 1, self.seed));
+        let d = unit32(hash32(x0 + 1, z0 + 1, self.seed));
+
+        lerp(lerp(a, b, tx), lerp(c, d, tx), tz)
+    }
+}
+
+// has to match GROUND_OCTAVES in the shader
+const GROUND_OCTAVES: u32 = 3;
+
+// the field biomes, placement, grass and wind are picked from, the shader samples the same one
+// with ground_noise so plants and the ground agree on where a biome is
+pub struct GroundNoise {
+    fbm: Fbm<ShaderValue>,
+}
+
+impl GroundNoise {
+    pub fn new(seed: u64) -> Self {
+        // the shader gets the seed as a float, which holds 24 bits exactly
+        let seed = (seed & 0xFF_FFFF) as u32;
+        Self {
+            fbm: Fbm::new(ShaderValue { seed }, GROUND_OCTAVES, 1.0),
+        }
+    }
+
+    pub fn shader_seed(&self) -> f32 {
+        self.fbm.source.seed as f32
+    }
+}
+
+impl Noise for GroundNoise {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        self.fbm.sample(x, z)
+    }
+}
+
+// lattice values per side of a cached block, blocks are what gets evicted
+const CACHE_BLOCK: i32 = 32;
+// blocks kept before the least recently used one goes, 64 blocks of 32 * 32 values
+const MAX_CACHE_BLOCKS: usize = 64;
+
+struct CacheBlock {
+    values: Vec<Option<f32>>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Cache {
+    blocks: HashMap<(i32, i32), CacheBlock>,
+    clock: u64,
+}
+
+// samples the source on a lattice `step` apart and interpolates between them, lattice values are
+// kept around so hot spots like the terrain around the camera only get computed once
+pub struct Cached<N: Noise> {
+    source: N,
+    step: f32,
+    cache: RefCell<Cache>,
+}
+
+impl<N: Noise> Cached<N> {
+    pub fn new(source: N, step: f32) -> Self {
+        Self {
+            source,
+            step,
+            cache: RefCell::default(),
+        }
+    }
+
+    fn lattice(&self, x: i32, z: i32) -> f32 {
+        let mut cache = self.cache.borrow_mut();
+        cache.clock += 1;
+        let clock = cache.clock;
+
+        let key = (x.div_euclid(CACHE_BLOCK), z.div_euclid(CACHE_BLOCK));
+        if !cache.blocks.contains_key(&key) && cache.blocks.len() >= MAX_CACHE_BLOCKS {
+            // only the block that went unused the longest goes, the rest stays warm
+            let oldest = cache
+                .blocks
+                .iter()
+                .min_by_key(|(_, block)| block.last_used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                cache.blocks.remove(&oldest);
+            }
+        }
+
+        let block = cache.blocks.entry(key).or_insert_with(|| CacheBlock {
+            values: vec![None; (CACHE_BLOCK * CACHE_BLOCK) as usize],
+            last_used: clock,
+        });
+        block.last_used = clock;
+        let i = (z.rem_euclid(CACHE_BLOCK) * CACHE_BLOCK + x.rem_euclid(CACHE_BLOCK)) as usize;
+        *block.values[i].get_or_insert_with(|| {
+            self.source
+                .sample(x as f32 * self.step, z as f32 * self.step)
+        })
+    }
+}
+
+impl<N: Noise> Noise for Cached<N> {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        let (x, z) = (x / self.step, z / self.step);
+        let (x0, z0) = (x.floor(), z.floor());
+        let (tx, tz) = (x - x0, z - z0);
+        let (x0, z0) = (x0 as i32, z0 as i32);
+
+        let a = self.lattice(x0, z0);
+        let b = self.lattice(x0 + 1, z0);
+        let c = self.lattice(x0, z0 + 1);
+        let d = self.lattice(x0 + 1, z0 + 1);
+        lerp(lerp(a, b, tx), lerp(c, d, tx), tz)
+    }
+}
+
+// interpolated random values on a lattice, cheaper and blockier than gradient noise
+pub fn value_noise(x: f32, z: f32, seed: u64) -> f32 {
+    let (x0, z0) = (x.floor(), z.floor());
+    let (tx, tz) = (smooth(x - x0), smooth(z - z0));
+    let (x0, z0) = (x0 as i32, z0 as i32);
+
+    let a = unit(hash(x0, z0, seed));
+    let b = unit(hash(x0 + 1, z0, seed));
+    let c = unit(hash(x0, z0 + 1, seed));
+    let d = unit(hash(x0 + 1, z0 + 1, seed));
+
+    lerp(lerp(a, b, tx), lerp(c, d, tx), tz)
+}
+
+pub fn hash(x: i32, z: i32, seed: u64) -> u64 {
+    let mut h = seed ^ (x as u32 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    h ^= (z as u32 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    h ^= h >> 33;
+    h
+}
+
+// matches hash32 in the shader
+fn hash32(x: i32, z: i32, seed: u32) -> u32 {
+    let mut h = seed ^ (x as u32).wrapping_mul(0x27D4_EB2D) ^ (z as u32).wrapping_mul(0x1656_67B1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297A_2D39);
+    h ^= h >> 15;
+    h
+}
+
+fn unit32(h: u32) -> f32 {
+    (h >> 8) as f32 / (1u32 << 24) as f32
+}
+
+fn unit(h: u64) -> f32 {
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn gradient(h: u64, x: f32, z: f32) -> f32 {
+    match h & 7 {
+        0 => x + z,
+        1 => -x + z,
+        2 => x - z,
+        3 => -x - z,
+        4 => x,
+        5 => -x,
+        6 => z,
+        _ => -z,
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn smooth(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::{
+        hash32, unit32, Cached, DomainWarp, Fbm, GroundNoise, Noise, Perlin, Simplex, Worley,
+        CACHE_BLOCK, GROUND_OCTAVES, MAX_CACHE_BLOCKS,
+    };
+
+    const SHADER: &str = include_str!("../res/shaders/shader.wgsl");
+
+    fn points() -> impl Iterator<Item = (f32, f32)> {
+        (-40..40).flat_map(|i| (-40..40).map(move |j| (i as f32 * 0.37, j as f32 * 0.53 - 3.1)))
+    }
+
+    fn noises(seed: u64) -> Vec<(&'static str, Box<dyn Noise>)> {
+        vec![
+            ("perlin", Box::new(Perlin { seed })),
+            ("simplex", Box::new(Simplex { seed })),
+            ("worley", Box::new(Worley { seed })),
+            ("fbm", Box::new(Fbm::new(Perlin { seed }, 4, 0.3))),
+            (
+                "domain warp",
+                Box::new(DomainWarp {
+                    source: Simplex { seed },
+                    warp: Perlin { seed: seed + 1 },
+                    strength: 2.0,
+                }),
+            ),
+            ("ground", Box::new(GroundNoise::new(seed))),
+        ]
+    }
+
+    #[test]
+    fn noise_stays_between_zero_and_one() {
+        for (name, noise) in noises(7) {
+            for (x, z) in points() {
+                let value = noise.sample(x, z);
+                assert!((0.0..=1.0).contains(&value), "{name} at {x}, {z}: {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_noise() {
+        for ((name, a), (_, b)) in noises(1234).into_iter().zip(noises(1234)) {
+            for (x, z) in points() {
+                assert_eq!(a.sample(x, z), b.sample(x, z), "{name} at {x}, {z}");
+            }
+        }
+        let (a, b) = (Perlin { seed: 1 }, Perlin { seed: 2 });
+        assert!(points().any(|(x, z)| a.sample(x, z) != b.sample(x, z)));
+    }
+
+    #[test]
+    fn cached_matches_the_source_on_the_lattice() {
+        let step = 0.125;
+        let cached = Cached::new(Perlin { seed: 3 }, step);
+        let source = Perlin { seed: 3 };
+        for i in -100..100 {
+            for j in [-70, -1, 0, 33] {
+                let (x, z) = (i as f32 * step, j as f32 * step);
+                assert_eq!(cached.sample(x, z), source.sample(x, z));
+            }
+        }
+    }
+
+    // counts how often the source gets sampled
+    struct Counting<'a>(&'a Cell<u32>);
+
+    impl Noise for Counting<'_> {
+        fn sample(&self, x: f32, z: f32) -> f32 {
+            self.0.set(self.0.get() + 1);
+            (x * 0.01 + z * 0.02).fract().abs()
+        }
+    }
+
+    #[test]
+    fn cache_evicts_the_least_recently_used_block() {
+        let count = Cell::new(0);
+        let cached = Cached::new(Counting(&count), 1.0);
+        let block = CACHE_BLOCK as f32;
+
+        cached.sample(0.5, 0.5);
+        // fills the cache while the first block keeps getting used
+        for i in 1..MAX_CACHE_BLOCKS as i32 + 4 {
+            cached.sample(i as f32 * block * 3.0 + 0.5, 0.5);
+            cached.sample(0.5, 0.5);
+        }
+        let before = count.get();
+        cached.sample(0.5, 0.5);
+        assert_eq!(count.get(), before, "the hot block was evicted");
+
+        // the block touched longest ago is gone
+        cached.sample(block * 3.0 + 0.5, 0.5);
+        assert!(count.get() > before);
+    }
+
+    // a line by line copy of hash32, value_noise and ground_noise in shader.wgsl
+    fn shader_ground_noise(p: (f32, f32), seed: u32) -> f32 {
+        fn value_noise(p: (f32, f32), seed: u32) -> f32 {
+            let cell = (p.0.floor(), p.1.floor());
+            let f = (p.0 - cell.0, p.1 - cell.1);
+            let t = (f.0 * f.0 * (3.0 - 2.0 * f.0), f.1 * f.1 * (3.0 - 2.0 * f.1));
+            let (x, z) = (cell.0 as i32, cell.1 as i32);
+            let mix = |a: f32, b: f32, t: f32| a * (1.0 - t) + b * t;
+
+            let a = unit32(hash32(x, z, seed));
+            let b = unit32(hash32(x + 1, z, seed));
+            let c = unit32(hash32(x, z + 1, seed));
+            let d = unit32(hash32(x + 1, z + 1, seed));
+            mix(mix(a, b, t.0), mix(c, d, t.0), t.1)
+        }
+
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        for octave in 0..3 {
+            let offset = octave as f32 * 17.31;
+            let value = value_noise((p.0 * frequency + offset, p.1 * frequency - offset), seed);
+            sum += (value * 2.0 - 1.0) * amplitude;
+            total += amplitude;
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+        (sum / total * 0.5 + 0.5).clamp(0.0, 1.0)
+    }
+
+    #[test]
+    fn ground_noise_matches_the_shader() {
+        for line in [
+            format!("const GROUND_OCTAVES: i32 = {GROUND_OCTAVES};"),
+            "var h = seed ^ (bitcast<u32>(x) * 0x27d4eb2du) ^ (bitcast<u32>(z) * 0x165667b1u);"
+                .to_string(),
+            "h *= 0x2c1b3c6du;".to_string(),
+            "h *= 0x297a2d39u;".to_string(),
+            "return f32(h >> 8u) / 16777216.0;".to_string(),
+            "let offset = f32(octave) * 17.31;".to_string(),
+        ] {
+            assert!(SHADER.contains(&line), "shader.wgsl lost `{line}`");
+        }
+
+        for seed in [0, 42, 0xFF_FFFF, 0x1234_5678_9ABC] {
+            let noise = GroundNoise::new(seed);
+            let shader_seed = noise.shader_seed();
+            assert_eq!(
+                shader_seed as u32 as f32, shader_seed,
+                "seed doesn't fit a float"
+            );
+            for (x, z) in points() {
+                let expected = shader_ground_noise((x, z), shader_seed as u32);
+                assert!((noise.sample(x, z) - expected).abs() < 1e-5, "{x}, {z}");
+            }
+        }
+    }
+}
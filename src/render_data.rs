@@ -14,7 +14,14 @@ pub struct RenderDataData {
     pub wind_direction: f32,
     pub wind_noise_scale: f32,
     pub wind_noise_strength: f32,
-    padding: [f32; 1],
+    // GroundNoise::shader_seed
+    pub noise_seed: f32,
+    pub biome_centers: [f32; 4],
+    pub biome_tints: [[f32; 4]; 4],
+    pub biome_scale: f32,
+    pub biome_blend: f32,
+    pub biome_count: f32,
+    biome_padding: [f32; 1],
 }
 
 pub struct RenderDataBindGroup {
@@ -45,7 +52,13 @@ impl RenderDataBindGroup {
             wind_direction: 0.0,
             wind_noise_scale: 0.05,
             wind_noise_strength: 5.0,
-            padding: [0.0; 1],
+            noise_seed: 0.0,
+            biome_centers: [0.0; 4],
+            biome_tints: [[1.0; 4]; 4],
+            biome_scale: 1.0,
+            biome_blend: 1.0,
+            biome_count: 0.0,
+            biome_padding: [0.0; 1],
         };
 
         let buffer_handle = renderer.create_uniform_buffer(bytemuck::cast_slice(&[data]));